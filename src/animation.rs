@@ -0,0 +1,428 @@
+//!
+//! Keyframe animation of the transform, opacity and tint of meshes.
+//!
+
+use notan::prelude::Color;
+use notan::math::{ Quat, Vec3 };
+
+/// Curve of the progress between two keyframes.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum Easing
+{
+  Linear,
+  /// Slow at both ends.
+  Cubic,
+  /// Overshoots and settles like a damped spring.
+  Spring,
+  /// Keeps the value of the first keyframe until the second is reached.
+  Step,
+}
+
+impl Easing
+{
+  /// Eased progress of the linear progress `t` in `0..=1`, 0 at 0 and 1 at 1.
+  pub fn apply( self, t : f32 ) -> f32
+  {
+    let t = t.clamp( 0.0, 1.0 );
+    match self
+    {
+      Easing::Linear => t,
+      Easing::Cubic if t < 0.5 => 4.0 * t * t * t,
+      Easing::Cubic => 1.0 - ( 2.0 - 2.0 * t ).powi( 3 ) * 0.5,
+      Easing::Spring =>
+      {
+        let spring = | t : f32 | 1.0 - ( -6.0 * t ).exp() * ( 4.0 * std::f32::consts::PI * t ).cos();
+        // Scaled so that the spring ends exactly at 1.
+        spring( t ) / spring( 1.0 )
+      }
+      Easing::Step => if t < 1.0 { 0.0 } else { 1.0 },
+    }
+  }
+}
+
+/// Values keyframes can interpolate.
+pub trait Lerp : Copy
+{
+  /// Value at `t` from `a` at 0 to `b` at 1, `t` may leave `0..=1` with overshooting easings.
+  fn lerp( a : Self, b : Self, t : f32 ) -> Self;
+}
+
+impl Lerp for f32
+{
+  fn lerp( a : Self, b : Self, t : f32 ) -> Self
+  {
+    a + ( b - a ) * t
+  }
+}
+
+impl Lerp for Vec3
+{
+  fn lerp( a : Self, b : Self, t : f32 ) -> Self
+  {
+    a.lerp( b, t )
+  }
+}
+
+impl Lerp for Quat
+{
+  fn lerp( a : Self, b : Self, t : f32 ) -> Self
+  {
+    a.slerp( b, t )
+  }
+}
+
+impl Lerp for Color
+{
+  fn lerp( a : Self, b : Self, t : f32 ) -> Self
+  {
+    Color::new( f32::lerp( a.r, b.r, t ), f32::lerp( a.g, b.g, t ), f32::lerp( a.b, b.b, t ), f32::lerp( a.a, b.a, t ) )
+  }
+}
+
+/// Value at a time, reached from the previous keyframe with `easing`.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Keyframe< T >
+{
+  /// Seconds from the start of the animation.
+  pub time : f32,
+  pub value : T,
+  pub easing : Easing,
+}
+
+impl< T > Keyframe< T >
+{
+  pub fn new( time : f32, value : T, easing : Easing ) -> Self
+  {
+    Self { time, value, easing }
+  }
+}
+
+/// Keyframes of one property, sorted by time.
+#[ derive( Debug, Clone, PartialEq ) ]
+pub struct Track< T >
+{
+  keyframes : Vec< Keyframe< T > >,
+}
+
+impl< T : Lerp > Track< T >
+{
+  pub fn new( mut keyframes : Vec< Keyframe< T > > ) -> Self
+  {
+    keyframes.sort_by( | a, b | a.time.total_cmp( &b.time ) );
+    Self { keyframes }
+  }
+
+  pub fn keyframes( &self ) -> &[ Keyframe< T > ]
+  {
+    &self.keyframes
+  }
+
+  /// Time of the last keyframe.
+  pub fn duration( &self ) -> f32
+  {
+    self.keyframes.last().map_or( 0.0, | keyframe | keyframe.time )
+  }
+
+  /// Value at `time`, the value of the nearest keyframe outside of the keyframes, `None` without keyframes.
+  pub fn sample( &self, time : f32 ) -> Option< T >
+  {
+    let next = self.keyframes.partition_point( | keyframe | keyframe.time <= time );
+    if next == 0
+    {
+      return self.keyframes.first().map( | keyframe | keyframe.value );
+    }
+    let previous = &self.keyframes[ next - 1 ];
+    let Some( next ) = self.keyframes.get( next ) else
+    {
+      return Some( previous.value );
+    };
+
+    let t = ( time - previous.time ) / ( next.time - previous.time );
+    Some( T::lerp( previous.value, next.value, next.easing.apply( t ) ) )
+  }
+}
+
+/// What happens when an animation reaches its end.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum Repeat
+{
+  /// Stop on the last keyframes.
+  Once,
+  /// Start over from the first keyframes.
+  Loop,
+  /// Play backward to the first keyframes, then forward again.
+  PingPong,
+}
+
+/// Reported by `Animation::advance`.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum AnimationEvent
+{
+  /// A looping animation reached an end and continues.
+  Looped,
+  /// An animation played once reached its end and stopped.
+  Completed,
+}
+
+/// Values of the animated properties at a time, `None` for properties without track.
+#[ derive( Debug, Clone, Copy, PartialEq, Default ) ]
+pub struct Pose
+{
+  pub translation : Option< Vec3 >,
+  pub rotation : Option< Quat >,
+  pub scale : Option< Vec3 >,
+  pub opacity : Option< f32 >,
+  pub tint : Option< Color >,
+}
+
+/// Tracks of the properties of a mesh played together.
+#[ derive( Debug, Clone, PartialEq ) ]
+pub struct Animation
+{
+  translation : Option< Track< Vec3 > >,
+  rotation : Option< Track< Quat > >,
+  scale : Option< Track< Vec3 > >,
+  opacity : Option< Track< f32 > >,
+  tint : Option< Track< Color > >,
+  repeat : Repeat,
+  time : f32,
+  finished : bool,
+}
+
+impl Animation
+{
+  /// Animation without tracks, add them with the builder methods.
+  pub fn new( repeat : Repeat ) -> Self
+  {
+    Self
+    {
+      translation : None,
+      rotation : None,
+      scale : None,
+      opacity : None,
+      tint : None,
+      repeat,
+      time : 0.0,
+      finished : false,
+    }
+  }
+
+  pub fn translation( mut self, track : Track< Vec3 > ) -> Self
+  {
+    self.translation = Some( track );
+    self
+  }
+
+  pub fn rotation( mut self, track : Track< Quat > ) -> Self
+  {
+    self.rotation = Some( track );
+    self
+  }
+
+  pub fn scale( mut self, track : Track< Vec3 > ) -> Self
+  {
+    self.scale = Some( track );
+    self
+  }
+
+  pub fn opacity( mut self, track : Track< f32 > ) -> Self
+  {
+    self.opacity = Some( track );
+    self
+  }
+
+  pub fn tint( mut self, track : Track< Color > ) -> Self
+  {
+    self.tint = Some( track );
+    self
+  }
+
+  pub fn repeat( &self ) -> Repeat
+  {
+    self.repeat
+  }
+
+  /// Time of the last keyframe of all tracks.
+  pub fn duration( &self ) -> f32
+  {
+    [
+      self.translation.as_ref().map( Track::duration ),
+      self.rotation.as_ref().map( Track::duration ),
+      self.scale.as_ref().map( Track::duration ),
+      self.opacity.as_ref().map( Track::duration ),
+      self.tint.as_ref().map( Track::duration ),
+    ]
+    .into_iter()
+    .flatten()
+    .fold( 0.0, f32::max )
+  }
+
+  /// Seconds played since the start, looping included.
+  pub fn time( &self ) -> f32
+  {
+    self.time
+  }
+
+  /// Whether an animation played once reached its end.
+  pub fn is_finished( &self ) -> bool
+  {
+    self.finished
+  }
+
+  /// Time in the tracks after `time` seconds of playing.
+  pub fn track_time( &self, time : f32 ) -> f32
+  {
+    let duration = self.duration();
+    if duration <= 0.0
+    {
+      return 0.0;
+    }
+    match self.repeat
+    {
+      Repeat::Once => time.min( duration ),
+      Repeat::Loop => time % duration,
+      Repeat::PingPong =>
+      {
+        let time = time % ( 2.0 * duration );
+        if time > duration { 2.0 * duration - time } else { time }
+      }
+    }
+  }
+
+  /// Play `delta` more seconds. Returns an event if an end was reached, once per call even if
+  /// several loops fit in `delta`.
+  pub fn advance( &mut self, delta : f32 ) -> Option< AnimationEvent >
+  {
+    if self.finished
+    {
+      return None;
+    }
+
+    let duration = self.duration();
+    let previous = self.time;
+    self.time += delta.max( 0.0 );
+    match self.repeat
+    {
+      Repeat::Once if self.time >= duration =>
+      {
+        self.time = duration;
+        self.finished = true;
+        Some( AnimationEvent::Completed )
+      }
+      Repeat::Once => None,
+      Repeat::Loop | Repeat::PingPong if duration <= 0.0 => None,
+      Repeat::Loop | Repeat::PingPong =>
+      {
+        let ends = | time : f32 | ( time / duration ) as u64;
+        ( ends( self.time ) > ends( previous ) ).then_some( AnimationEvent::Looped )
+      }
+    }
+  }
+
+  /// Values of the animated properties at the current time.
+  pub fn pose( &self ) -> Pose
+  {
+    let time = self.track_time( self.time );
+    Pose
+    {
+      translation : self.translation.as_ref().and_then( | track | track.sample( time ) ),
+      rotation : self.rotation.as_ref().and_then( | track | track.sample( time ) ),
+      scale : self.scale.as_ref().and_then( | track | track.sample( time ) ),
+      opacity : self.opacity.as_ref().and_then( | track | track.sample( time ) ),
+      tint : self.tint.as_ref().and_then( | track | track.sample( time ) ),
+    }
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn close( a : f32, b : f32 ) -> bool
+  {
+    ( a - b ).abs() < 1e-5
+  }
+
+  fn ramp( easing : Easing ) -> Track< f32 >
+  {
+    Track::new( vec![ Keyframe::new( 2.0, 1.0, easing ), Keyframe::new( 0.0, 0.0, Easing::Linear ) ] )
+  }
+
+  #[ test ]
+  fn easings_start_at_0_and_end_at_1()
+  {
+    for easing in [ Easing::Linear, Easing::Cubic, Easing::Spring, Easing::Step ]
+    {
+      assert!( close( easing.apply( 0.0 ), 0.0 ), "{easing:?}" );
+      assert!( close( easing.apply( 1.0 ), 1.0 ), "{easing:?}" );
+    }
+    assert!( close( Easing::Cubic.apply( 0.5 ), 0.5 ) );
+    assert_eq!( Easing::Step.apply( 0.99 ), 0.0 );
+    assert!( ( 0..10 ).map( | t | Easing::Spring.apply( t as f32 / 10.0 ) ).any( | value | value > 1.0 ) );
+  }
+
+  #[ test ]
+  fn sample_interpolates_with_the_easing_of_the_next_keyframe()
+  {
+    let track = ramp( Easing::Linear );
+    assert_eq!( track.duration(), 2.0 );
+    assert_eq!( track.sample( -1.0 ), Some( 0.0 ) );
+    assert!( close( track.sample( 0.5 ).unwrap(), 0.25 ) );
+    assert_eq!( track.sample( 3.0 ), Some( 1.0 ) );
+    assert_eq!( ramp( Easing::Step ).sample( 1.5 ), Some( 0.0 ) );
+    assert_eq!( Track::< f32 >::new( vec![] ).sample( 1.0 ), None );
+  }
+
+  #[ test ]
+  fn once_completes_and_holds()
+  {
+    let mut animation = Animation::new( Repeat::Once ).opacity( ramp( Easing::Linear ) );
+    assert_eq!( animation.advance( 1.0 ), None );
+    assert_eq!( animation.advance( 5.0 ), Some( AnimationEvent::Completed ) );
+    assert!( animation.is_finished() );
+    assert_eq!( animation.time(), 2.0 );
+    assert_eq!( animation.advance( 1.0 ), None );
+    assert_eq!( animation.pose().opacity, Some( 1.0 ) );
+  }
+
+  #[ test ]
+  fn loop_reports_once_per_advance()
+  {
+    let mut animation = Animation::new( Repeat::Loop ).opacity( ramp( Easing::Linear ) );
+    assert_eq!( animation.advance( 1.5 ), None );
+    assert_eq!( animation.advance( 5.0 ), Some( AnimationEvent::Looped ) );
+    assert!( close( animation.pose().opacity.unwrap(), 0.25 ) );
+    assert!( !animation.is_finished() );
+  }
+
+  #[ test ]
+  fn ping_pong_plays_backward()
+  {
+    let animation = Animation::new( Repeat::PingPong ).opacity( ramp( Easing::Linear ) );
+    assert!( close( animation.track_time( 1.5 ), 1.5 ) );
+    assert!( close( animation.track_time( 2.5 ), 1.5 ) );
+    assert!( close( animation.track_time( 4.5 ), 0.5 ) );
+  }
+
+  #[ test ]
+  fn pose_has_only_the_animated_properties()
+  {
+    let animation = Animation::new( Repeat::Once )
+    .translation( Track::new( vec![ Keyframe::new( 0.0, Vec3::ONE, Easing::Linear ) ] ) )
+    .tint( Track::new( vec![ Keyframe::new( 1.0, Color::RED, Easing::Linear ) ] ) );
+    assert_eq!( animation.duration(), 1.0 );
+    let pose = animation.pose();
+    assert_eq!( ( pose.translation, pose.tint ), ( Some( Vec3::ONE ), Some( Color::RED ) ) );
+    assert_eq!( ( pose.rotation, pose.scale, pose.opacity ), ( None, None, None ) );
+  }
+
+  #[ test ]
+  fn without_keyframes_nothing_advances()
+  {
+    let mut animation = Animation::new( Repeat::Loop );
+    assert_eq!( animation.duration(), 0.0 );
+    assert_eq!( animation.advance( 1.0 ), None );
+    assert_eq!( animation.track_time( 1.0 ), 0.0 );
+  }
+}
@@ -0,0 +1,375 @@
+//!
+//! Texture atlases : images packed into one texture, so that meshes drawing them share a bind.
+//!
+
+use std::collections::{ BTreeMap, HashSet };
+use notan::prelude::*;
+use notan::math::Rect;
+use crate::error::Error;
+use crate::AlphaMode;
+
+/// RGBA image, rows from the top.
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+pub struct Image
+{
+  pub width : u32,
+  pub height : u32,
+  /// 4 bytes per texel.
+  pub texels : Vec< u8 >,
+}
+
+impl Image
+{
+  /// Decode a PNG file.
+  pub fn from_png( bytes : &[ u8 ] ) -> Result< Self, String >
+  {
+    let image = image::load_from_memory_with_format( bytes, image::ImageFormat::Png )
+    .map_err( | error | error.to_string() )?
+    .to_rgba8();
+    Ok( Self { width : image.width(), height : image.height(), texels : image.into_raw() } )
+  }
+
+  fn texel( &self, x : u32, y : u32 ) -> &[ u8 ]
+  {
+    let start = ( y * self.width + x ) as usize * 4;
+    &self.texels[ start..start + 4 ]
+  }
+}
+
+/// Packed images with the rectangle of each in texture coordinates.
+#[ derive( Debug, Clone, PartialEq ) ]
+pub struct Atlas
+{
+  image : Image,
+  regions : BTreeMap< String, Rect >,
+}
+
+impl Atlas
+{
+  /// Texels of the atlas.
+  pub fn image( &self ) -> &Image
+  {
+    &self.image
+  }
+
+  /// Rectangles of the packed images by name, in `0..=1` texture coordinates without the extrusion.
+  pub fn regions( &self ) -> &BTreeMap< String, Rect >
+  {
+    &self.regions
+  }
+
+  pub fn uv_rect( &self, name : &str ) -> Option< Rect >
+  {
+    self.regions.get( name ).copied()
+  }
+
+  /// Texture of the atlas, linearly filtered : the extrusion keeps the filter from reaching
+  /// the neighbours of an image. Texels are premultiplied for `AlphaMode::Premultiplied`.
+  pub fn texture( &self, gfx : &mut Graphics, alpha_mode : AlphaMode ) -> Result< Texture, Error >
+  {
+    let builder = gfx
+    .create_texture()
+    .from_bytes( &self.image.texels, self.image.width as i32, self.image.height as i32 )
+    .with_filter( TextureFilter::Linear, TextureFilter::Linear );
+    match alpha_mode
+    {
+      AlphaMode::Straight => builder,
+      AlphaMode::Premultiplied => builder.with_premultiplied_alpha(),
+    }
+    .build().map_err( Error::texture )
+  }
+}
+
+/// Position of the top left corner of each cell packed in rows of `width` texels, `padding`
+/// texels apart and from the edges, with the height used. `None` if a cell is wider than the rows.
+fn pack( cells : &[ ( u32, u32 ) ], width : u32, padding : u32 ) -> Option< ( Vec< ( u32, u32 ) >, u32 ) >
+{
+  // Tallest first, rows waste less height.
+  let mut order : Vec< usize > = ( 0..cells.len() ).collect();
+  order.sort_by_key( | &index | std::cmp::Reverse( cells[ index ].1 ) );
+
+  let mut positions = vec![ ( 0, 0 ); cells.len() ];
+  let ( mut x, mut y, mut row ) = ( padding, padding, 0 );
+  for index in order
+  {
+    let ( cell_width, cell_height ) = cells[ index ];
+    if padding + cell_width + padding > width
+    {
+      return None;
+    }
+    if x + cell_width + padding > width
+    {
+      x = padding;
+      y += row + padding;
+      row = 0;
+    }
+    positions[ index ] = ( x, y );
+    x += cell_width + padding;
+    row = row.max( cell_height );
+  }
+  Some( ( positions, y + row + padding ) )
+}
+
+/// Images to pack into an atlas.
+#[ derive( Debug, Clone ) ]
+pub struct AtlasBuilder
+{
+  images : Vec< ( String, Image ) >,
+  padding : u32,
+  extrude : u32,
+  max_size : u32,
+}
+
+impl Default for AtlasBuilder
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      images : vec![],
+      padding : 2,
+      extrude : 1,
+      max_size : 4096,
+    }
+  }
+}
+
+impl AtlasBuilder
+{
+  /// Transparent texels between the images and around the atlas.
+  pub fn padding( mut self, padding : u32 ) -> Self
+  {
+    self.padding = padding;
+    self
+  }
+
+  /// Texels the edges of each image are repeated outward, so that filtering at the edges of a
+  /// region samples the image and not the padding.
+  pub fn extrude( mut self, extrude : u32 ) -> Self
+  {
+    self.extrude = extrude;
+    self
+  }
+
+  /// Largest width and height of the atlas.
+  pub fn max_size( mut self, max_size : u32 ) -> Self
+  {
+    self.max_size = max_size;
+    self
+  }
+
+  pub fn add( mut self, name : impl Into< String >, image : Image ) -> Self
+  {
+    self.images.push( ( name.into(), image ) );
+    self
+  }
+
+  /// Add the PNG files of `directory`, named by their file name without extension.
+  #[ cfg( not( target_arch = "wasm32" ) ) ]
+  pub fn add_dir( mut self, directory : impl AsRef< std::path::Path > ) -> Result< Self, Error >
+  {
+    let directory = directory.as_ref();
+    let error = | path : &std::path::Path, message : String | Error::Asset { path : path.display().to_string(), message };
+
+    let mut paths : Vec< _ > = std::fs::read_dir( directory )
+    .map_err( | message | error( directory, message.to_string() ) )?
+    .filter_map( | entry | entry.ok().map( | entry | entry.path() ) )
+    .filter( | path | path.extension().is_some_and( | extension | extension.eq_ignore_ascii_case( "png" ) ) )
+    .collect();
+    paths.sort();
+
+    for path in paths
+    {
+      let bytes = std::fs::read( &path ).map_err( | message | error( &path, message.to_string() ) )?;
+      let image = Image::from_png( &bytes ).map_err( | message | error( &path, message ) )?;
+      let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+      self = self.add( name, image );
+    }
+    Ok( self )
+  }
+
+  /// Pack the images in the narrowest power of two width that keeps the atlas within the max size.
+  pub fn build( &self ) -> Result< Atlas, Error >
+  {
+    let mut names = HashSet::new();
+    for ( name, image ) in &self.images
+    {
+      if !names.insert( name.as_str() )
+      {
+        return Err( Error::Atlas( format!( "two images are named {name}" ) ) );
+      }
+      if image.width == 0 || image.height == 0
+      {
+        return Err( Error::Atlas( format!( "image {name} is empty" ) ) );
+      }
+      if image.texels.len() != image.width as usize * image.height as usize * 4
+      {
+        return Err( Error::Atlas( format!( "image {name} does not have 4 bytes per texel" ) ) );
+      }
+    }
+
+    let cells : Vec< ( u32, u32 ) > = self.images
+    .iter()
+    .map( | ( _, image ) | ( image.width + 2 * self.extrude, image.height + 2 * self.extrude ) )
+    .collect();
+    let area : u64 = cells.iter().map( | &( width, height ) | u64::from( width + self.padding ) * u64::from( height + self.padding ) ).sum();
+
+    let mut width = ( ( area as f64 ).sqrt() as u32 ).max( 1 ).next_power_of_two();
+    let ( positions, height ) = loop
+    {
+      if width > self.max_size
+      {
+        return Err( Error::Atlas( format!( "the images do not fit in {0} by {0} texels", self.max_size ) ) );
+      }
+      match pack( &cells, width, self.padding )
+      {
+        Some( ( positions, height ) ) if height <= self.max_size => break ( positions, height ),
+        _ => width *= 2,
+      }
+    };
+
+    let mut texels = vec![ 0; width as usize * height as usize * 4 ];
+    let mut regions = BTreeMap::new();
+    for ( ( ( name, image ), &( x, y ) ), &( cell_width, cell_height ) ) in self.images.iter().zip( &positions ).zip( &cells )
+    {
+      // Texels of the extrusion repeat the nearest texel of the image.
+      for cell_y in 0..cell_height
+      {
+        let source_y = cell_y.saturating_sub( self.extrude ).min( image.height - 1 );
+        for cell_x in 0..cell_width
+        {
+          let source_x = cell_x.saturating_sub( self.extrude ).min( image.width - 1 );
+          let start = ( ( y + cell_y ) * width + x + cell_x ) as usize * 4;
+          texels[ start..start + 4 ].copy_from_slice( image.texel( source_x, source_y ) );
+        }
+      }
+
+      regions.insert( name.clone(), Rect
+      {
+        x : ( x + self.extrude ) as f32 / width as f32,
+        y : ( y + self.extrude ) as f32 / height as f32,
+        width : image.width as f32 / width as f32,
+        height : image.height as f32 / height as f32,
+      });
+    }
+
+    Ok( Atlas { image : Image { width, height, texels }, regions } )
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  /// Image whose texels all differ, the red and green channels are the coordinates.
+  fn image( width : u32, height : u32, blue : u8 ) -> Image
+  {
+    let texels = ( 0..height ).flat_map( | y | ( 0..width ).flat_map( move | x | [ x as u8, y as u8, blue, 255 ] ) ).collect();
+    Image { width, height, texels }
+  }
+
+  /// Rectangle of a region in texels : left, top, right and bottom, exclusive.
+  fn texels( atlas : &Atlas, name : &str ) -> ( u32, u32, u32, u32 )
+  {
+    let Rect { x, y, width, height } = atlas.uv_rect( name ).unwrap();
+    let ( atlas_width, atlas_height ) = ( atlas.image().width as f32, atlas.image().height as f32 );
+    let left = ( x * atlas_width ).round() as u32;
+    let top = ( y * atlas_height ).round() as u32;
+    ( left, top, left + ( width * atlas_width ).round() as u32, top + ( height * atlas_height ).round() as u32 )
+  }
+
+  fn sizes() -> AtlasBuilder
+  {
+    AtlasBuilder::default()
+    .padding( 2 )
+    .extrude( 1 )
+    .add( "wide", image( 20, 4, 1 ) )
+    .add( "tall", image( 5, 17, 2 ) )
+    .add( "square", image( 9, 9, 3 ) )
+    .add( "dot", image( 1, 1, 4 ) )
+  }
+
+  #[ test ]
+  fn regions_do_not_overlap_with_padding()
+  {
+    let atlas = sizes().build().unwrap();
+    let ( width, height ) = ( atlas.image().width, atlas.image().height );
+    // Cells with their extrusion, which the padding separates.
+    let cells : Vec< _ > = atlas.regions().keys().map( | name |
+    {
+      let ( left, top, right, bottom ) = texels( &atlas, name );
+      ( left - 1, top - 1, right + 1, bottom + 1 )
+    })
+    .collect();
+
+    for ( index, &( left, top, right, bottom ) ) in cells.iter().enumerate()
+    {
+      assert!( left >= 2 && top >= 2 && right + 2 <= width && bottom + 2 <= height );
+      for &( other_left, other_top, other_right, other_bottom ) in &cells[ index + 1.. ]
+      {
+        let apart = right + 2 <= other_left || other_right + 2 <= left || bottom + 2 <= other_top || other_bottom + 2 <= top;
+        assert!( apart, "{:?} and {:?} are closer than the padding", ( left, top, right, bottom ), ( other_left, other_top, other_right, other_bottom ) );
+      }
+    }
+  }
+
+  #[ test ]
+  fn regions_hold_the_images_without_extrusion()
+  {
+    let builder = sizes();
+    let atlas = builder.build().unwrap();
+    for ( name, source ) in &builder.images
+    {
+      let ( left, top, right, bottom ) = texels( &atlas, name );
+      assert_eq!( ( right - left, bottom - top ), ( source.width, source.height ) );
+      for y in 0..source.height
+      {
+        for x in 0..source.width
+        {
+          assert_eq!( atlas.image().texel( left + x, top + y ), source.texel( x, y ) );
+        }
+      }
+    }
+  }
+
+  #[ test ]
+  fn extrusion_repeats_the_edges()
+  {
+    let atlas = AtlasBuilder::default().extrude( 2 ).add( "image", image( 3, 2, 7 ) ).build().unwrap();
+    let ( left, top, right, bottom ) = texels( &atlas, "image" );
+    let texel = | x : u32, y : u32 | atlas.image().texel( x, y ).to_vec();
+    for offset in 1..=2
+    {
+      // Corners, then the middle of each edge.
+      assert_eq!( texel( left - offset, top - offset ), texel( left, top ) );
+      assert_eq!( texel( right - 1 + offset, bottom - 1 + offset ), texel( right - 1, bottom - 1 ) );
+      assert_eq!( texel( left + 1, top - offset ), texel( left + 1, top ) );
+      assert_eq!( texel( left + 1, bottom - 1 + offset ), texel( left + 1, bottom - 1 ) );
+      assert_eq!( texel( left - offset, top + 1 ), texel( left, top + 1 ) );
+      assert_eq!( texel( right - 1 + offset, top ), texel( right - 1, top ) );
+    }
+  }
+
+  #[ test ]
+  fn duplicate_names_are_refused()
+  {
+    let result = AtlasBuilder::default().add( "image", image( 1, 1, 0 ) ).add( "image", image( 2, 2, 0 ) ).build();
+    assert!( matches!( result, Err( Error::Atlas( _ ) ) ) );
+  }
+
+  #[ test ]
+  fn empty_images_are_refused()
+  {
+    let result = AtlasBuilder::default().add( "empty", Image { width : 0, height : 4, texels : vec![] } ).build();
+    assert!( matches!( result, Err( Error::Atlas( _ ) ) ) );
+  }
+
+  #[ test ]
+  fn images_larger_than_the_max_size_are_refused()
+  {
+    let builder = AtlasBuilder::default().max_size( 16 ).add( "image", image( 14, 2, 0 ) );
+    assert!( matches!( builder.build(), Err( Error::Atlas( _ ) ) ) );
+    assert!( builder.padding( 0 ).extrude( 0 ).build().is_ok() );
+  }
+}
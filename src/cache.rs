@@ -0,0 +1,106 @@
+//!
+//! Reference counted cache of values shared by key.
+//!
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Values created on first use and dropped when their last user releases them.
+#[ derive( Debug ) ]
+pub struct RefCache< K, V >
+{
+  entries : HashMap< K, ( V, usize ) >,
+}
+
+impl< K, V > Default for RefCache< K, V >
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      entries : HashMap::new(),
+    }
+  }
+}
+
+impl< K : Eq + Hash, V : Clone > RefCache< K, V >
+{
+  /// Value of `key`, created with `create` if nobody uses it, counting one more user. Nothing is
+  /// cached or counted if the creation fails.
+  pub fn try_acquire< E >( &mut self, key : K, create : impl FnOnce() -> Result< V, E > ) -> Result< V, E >
+  {
+    if let Some( ( value, users ) ) = self.entries.get_mut( &key )
+    {
+      *users += 1;
+      return Ok( value.clone() );
+    }
+    let value = create()?;
+    self.entries.insert( key, ( value.clone(), 1 ) );
+    Ok( value )
+  }
+
+  /// Count one user of `key` less. Returns the value when it was the last user, the cache drops it.
+  pub fn release( &mut self, key : &K ) -> Option< V >
+  {
+    let ( _, users ) = self.entries.get_mut( key )?;
+    *users -= 1;
+    if *users > 0
+    {
+      return None;
+    }
+    self.entries.remove( key ).map( | ( value, _ ) | value )
+  }
+
+  /// Number of users of `key`, 0 if it is not cached.
+  pub fn users( &self, key : &K ) -> usize
+  {
+    self.entries.get( key ).map_or( 0, | ( _, users ) | *users )
+  }
+
+  /// Number of cached values.
+  pub fn len( &self ) -> usize
+  {
+    self.entries.len()
+  }
+
+  pub fn is_empty( &self ) -> bool
+  {
+    self.entries.is_empty()
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  #[ test ]
+  fn release_returns_the_value_to_the_last_user()
+  {
+    let mut cache = RefCache::default();
+    assert_eq!( cache.try_acquire( "quad", || Ok::< _, () >( 1 ) ), Ok( 1 ) );
+    // Already cached, not created again.
+    assert_eq!( cache.try_acquire( "quad", || Ok::< _, () >( 2 ) ), Ok( 1 ) );
+    assert_eq!( cache.users( &"quad" ), 2 );
+
+    assert_eq!( cache.release( &"quad" ), None );
+    assert_eq!( cache.users( &"quad" ), 1 );
+    assert_eq!( cache.release( &"quad" ), Some( 1 ) );
+    assert_eq!( cache.users( &"quad" ), 0 );
+    assert!( cache.is_empty() );
+    assert_eq!( cache.release( &"quad" ), None );
+  }
+
+  #[ test ]
+  fn failed_creation_is_neither_cached_nor_counted()
+  {
+    let mut cache = RefCache::< _, i32 >::default();
+    assert_eq!( cache.try_acquire( "quad", || Err( "no buffer" ) ), Err( "no buffer" ) );
+    assert_eq!( cache.users( &"quad" ), 0 );
+    assert!( cache.is_empty() );
+
+    assert_eq!( cache.try_acquire( "quad", || Ok::< _, &str >( 1 ) ), Ok( 1 ) );
+    assert_eq!( cache.users( &"quad" ), 1 );
+    assert_eq!( cache.len(), 1 );
+  }
+}
@@ -0,0 +1,97 @@
+//!
+//! Camera placing the scene in front of the viewer.
+//!
+
+use notan::math::{ Mat4, Vec3 };
+
+/// How the view volume of a camera is mapped to clip space.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub enum Projection
+{
+  /// Farther things look smaller. `fov_y` is the vertical field of view in radians.
+  Perspective
+  {
+    fov_y : f32,
+    near : f32,
+    far : f32,
+  },
+  /// Size does not change with distance. `half_height` is half the visible height in world units.
+  Orthographic
+  {
+    half_height : f32,
+    near : f32,
+    far : f32,
+  },
+}
+
+/// Right-handed camera looking from `position` at `target`.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Camera
+{
+  pub position : Vec3,
+  pub target : Vec3,
+  pub up : Vec3,
+  pub projection : Projection,
+}
+
+impl Default for Camera
+{
+  /// Orthographic camera one unit in front of the origin showing `-1..=1` vertically, so a scene
+  /// placed in clip space keeps its layout up to the aspect ratio.
+  fn default() -> Self
+  {
+    Self
+    {
+      position : Vec3::Z,
+      target : Vec3::ZERO,
+      up : Vec3::Y,
+      projection : Projection::Orthographic
+      {
+        half_height : 1.0,
+        near : 0.0,
+        far : 2.0,
+      },
+    }
+  }
+}
+
+impl Camera
+{
+  /// Camera looking from `position` at `target` with y up.
+  pub fn new( position : impl Into< Vec3 >, target : impl Into< Vec3 >, projection : Projection ) -> Self
+  {
+    Self
+    {
+      position : position.into(),
+      target : target.into(),
+      up : Vec3::Y,
+      projection,
+    }
+  }
+
+  /// World to view space, the camera looks down -z.
+  pub fn view( &self ) -> Mat4
+  {
+    Mat4::look_at_rh( self.position, self.target, self.up )
+  }
+
+  /// View to clip space for a target `aspect` times wider than high.
+  pub fn projection( &self, aspect : f32 ) -> Mat4
+  {
+    match self.projection
+    {
+      Projection::Perspective { fov_y, near, far } => Mat4::perspective_rh_gl( fov_y, aspect, near, far ),
+      Projection::Orthographic { half_height, near, far } =>
+      {
+        let half_width = half_height * aspect;
+        Mat4::orthographic_rh_gl( -half_width, half_width, -half_height, half_height, near, far )
+      }
+    }
+  }
+
+  /// World to clip space for a target `aspect` times wider than high.
+  pub fn view_projection( &self, aspect : f32 ) -> Mat4
+  {
+    self.projection( aspect ) * self.view()
+  }
+}
@@ -0,0 +1,138 @@
+//!
+//! Time of the update phase, with an optional fixed timestep.
+//!
+
+/// Fixed steps run at most per tick, the time beyond is dropped so a long frame, such as one
+/// after the page was hidden, does not freeze the app catching up.
+pub const MAX_FIXED_STEPS : usize = 8;
+
+/// Updates to run for a tick : `count` updates of `delta` seconds each.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Steps
+{
+  pub count : usize,
+  pub delta : f32,
+}
+
+/// Elapsed time of the updates, advanced by the time of every frame.
+#[ derive( Debug, Clone, Copy, PartialEq, Default ) ]
+pub struct Clock
+{
+  fixed_step : Option< f32 >,
+  delta : f32,
+  elapsed : f32,
+  accumulator : f32,
+}
+
+impl Clock
+{
+  /// Clock running one update per frame, or updates of `fixed_step` seconds if it is some.
+  pub fn new( fixed_step : Option< f32 > ) -> Self
+  {
+    Self
+    {
+      fixed_step : fixed_step.filter( | step | *step > 0.0 ),
+      ..Self::default()
+    }
+  }
+
+  pub fn fixed_step( &self ) -> Option< f32 >
+  {
+    self.fixed_step
+  }
+
+  /// Time of the last frame in seconds.
+  pub fn delta( &self ) -> f32
+  {
+    self.delta
+  }
+
+  /// Seconds of updates run so far.
+  pub fn elapsed( &self ) -> f32
+  {
+    self.elapsed
+  }
+
+  /// Fraction of a fixed step left over after the updates of the last tick, to interpolate between
+  /// the last two steps. 0 without fixed step.
+  pub fn alpha( &self ) -> f32
+  {
+    self.fixed_step.map_or( 0.0, | step | self.accumulator / step )
+  }
+
+  /// Advance by a frame of `delta` seconds, returns the updates to run.
+  pub fn tick( &mut self, delta : f32 ) -> Steps
+  {
+    let delta = delta.max( 0.0 );
+    self.delta = delta;
+
+    let Some( step ) = self.fixed_step else
+    {
+      self.elapsed += delta;
+      return Steps { count : 1, delta };
+    };
+
+    self.accumulator += delta;
+    let count = ( ( self.accumulator / step ) as usize ).min( MAX_FIXED_STEPS );
+    self.accumulator -= count as f32 * step;
+    if self.accumulator >= step
+    {
+      // Behind by more than `MAX_FIXED_STEPS` steps, drop the whole steps left.
+      self.accumulator %= step;
+    }
+    self.elapsed += count as f32 * step;
+    Steps { count, delta : step }
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  #[ test ]
+  fn remainder_carries_to_the_next_tick()
+  {
+    let mut clock = Clock::new( Some( 0.25 ) );
+    assert_eq!( clock.tick( 0.375 ), Steps { count : 1, delta : 0.25 } );
+    assert_eq!( clock.alpha(), 0.5 );
+    assert_eq!( clock.tick( 0.375 ), Steps { count : 2, delta : 0.25 } );
+    assert_eq!( clock.alpha(), 0.0 );
+    assert_eq!( clock.elapsed(), 0.75 );
+  }
+
+  #[ test ]
+  fn long_frames_drop_the_steps_beyond_the_cap()
+  {
+    let mut clock = Clock::new( Some( 0.25 ) );
+    // 12.5 steps : 8 run, the 4 whole ones left are dropped, the half step is kept.
+    assert_eq!( clock.tick( 3.125 ).count, MAX_FIXED_STEPS );
+    assert_eq!( clock.alpha(), 0.5 );
+    assert_eq!( clock.elapsed(), 2.0 );
+    assert_eq!( clock.tick( 0.125 ).count, 1 );
+  }
+
+  #[ test ]
+  fn negative_delta_counts_as_zero()
+  {
+    let mut clock = Clock::new( Some( 0.25 ) );
+    clock.tick( 0.125 );
+    assert_eq!( clock.tick( -1.0 ).count, 0 );
+    assert_eq!( clock.delta(), 0.0 );
+    assert_eq!( clock.alpha(), 0.5 );
+
+    let mut clock = Clock::new( None );
+    assert_eq!( clock.tick( -1.0 ), Steps { count : 1, delta : 0.0 } );
+    assert_eq!( clock.elapsed(), 0.0 );
+  }
+
+  #[ test ]
+  fn zero_fixed_step_runs_one_update_per_frame()
+  {
+    let mut clock = Clock::new( Some( 0.0 ) );
+    assert_eq!( clock.fixed_step(), None );
+    assert_eq!( clock.tick( 0.5 ), Steps { count : 1, delta : 0.5 } );
+    assert_eq!( clock.elapsed(), 0.5 );
+    assert_eq!( clock.alpha(), 0.0 );
+  }
+}
@@ -0,0 +1,293 @@
+//!
+//! Depth peeling pass, see `peel` for the reference it follows.
+//!
+
+use notan::prelude::*;
+use crate::error::Error;
+use crate::lib::{ bind_uniform_blocks, mesh_pipeline, Coverage, Mesh, Occluders, RenderClass, View };
+use crate::screen_quad::ScreenQuad;
+
+const FRAG_PEEL_COLOR : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision highp float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  layout( location = 1 ) uniform sampler2D u_previous_depth;
+  layout( set = 2, binding = 0 ) uniform MeshMaterial
+  {
+    vec4 tint;
+    float opacity;
+    float premultiplied;
+    float cutoff;
+  };
+
+  float unpack_depth( vec4 packed )
+  {
+    return dot( packed, vec4( 1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0 ) );
+  }
+
+  void main()
+  {
+    vec4 texel = texture( u_texture, v_uv );
+    // The pass works on straight colors, premultiplied texels are divided back by their alpha.
+    texel.rgb /= mix( 1.0, max( texel.a, 0.00001 ), premultiplied );
+    texel *= tint;
+    texel.a *= opacity;
+    float previous = unpack_depth( texelFetch( u_previous_depth, ivec2( gl_FragCoord.xy ), 0 ) );
+    if( texel.a <= 0.0 || texel.a < cutoff || gl_FragCoord.z <= previous + 0.00001 )
+    {
+      discard;
+    }
+    color = texel;
+  }
+  "#
+};
+
+const FRAG_PEEL_DEPTH : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision highp float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  layout( location = 1 ) uniform sampler2D u_previous_depth;
+  layout( set = 2, binding = 0 ) uniform MeshMaterial
+  {
+    vec4 tint;
+    float opacity;
+    float premultiplied;
+    float cutoff;
+  };
+
+  float unpack_depth( vec4 packed )
+  {
+    return dot( packed, vec4( 1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0 ) );
+  }
+
+  vec4 pack_depth( float depth )
+  {
+    vec4 packed = fract( depth * vec4( 1.0, 255.0, 65025.0, 16581375.0 ) );
+    return packed - packed.yzww * vec4( 1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0 );
+  }
+
+  void main()
+  {
+    vec4 texel = texture( u_texture, v_uv ) * tint;
+    texel.a *= opacity;
+    float previous = unpack_depth( texelFetch( u_previous_depth, ivec2( gl_FragCoord.xy ), 0 ) );
+    if( texel.a <= 0.0 || texel.a < cutoff || gl_FragCoord.z <= previous + 0.00001 )
+    {
+      discard;
+    }
+    color = pack_depth( gl_FragCoord.z );
+  }
+  "#
+};
+
+const FRAG_UNDER : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_layer;
+
+  void main()
+  {
+    vec4 texel = texture( u_layer, v_uv );
+    color = vec4( texel.rgb * texel.a, texel.a );
+  }
+  "#
+};
+
+const FRAG_RESOLVE : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_front;
+
+  void main()
+  {
+    color = texture( u_front, v_uv );
+  }
+  "#
+};
+
+/// Peels the nearest translucent layers one pass at a time and composites them from front to back
+/// before putting them over the screen. Layers past `layers` are dropped, at least one is peeled.
+///
+/// The depth of each layer is packed in the color of an offscreen target, as notan can not sample
+/// depth attachments.
+#[ derive( Debug ) ]
+pub struct DepthPeeling
+{
+  layers : usize,
+  layer : RenderTexture,
+  depths : [ RenderTexture; 2 ],
+  front : RenderTexture,
+  occluders : Occluders,
+  color_pipeline : Pipeline,
+  depth_pipeline : Pipeline,
+  under_pipeline : Pipeline,
+  resolve_pipeline : Pipeline,
+  quad : ScreenQuad,
+}
+
+impl DepthPeeling
+{
+  pub fn new( gfx : &mut Graphics, layers : usize, coverage : Coverage ) -> Result< Self, Error >
+  {
+    if layers == 0
+    {
+      return Err( Error::Config( "depth peeling needs at least one layer".to_string() ) );
+    }
+
+    let layer = Self::target( gfx, true )?;
+    let depths = [ Self::target( gfx, true )?, Self::target( gfx, true )? ];
+    let front = Self::target( gfx, false )?;
+
+    let depth_write = DepthStencil
+    {
+      write : true,
+      compare : CompareMode::Less,
+    };
+
+    let occluders = Occluders::new( gfx, coverage )?;
+    let color_pipeline = mesh_pipeline( gfx, &FRAG_PEEL_COLOR )
+    .with_texture_location( 1, "u_previous_depth" )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    let depth_pipeline = mesh_pipeline( gfx, &FRAG_PEEL_DEPTH )
+    .with_texture_location( 1, "u_previous_depth" )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    bind_uniform_blocks( gfx, &[ &color_pipeline, &depth_pipeline ] )?;
+
+    let under = BlendMode::new( BlendFactor::InverseDestinationAlpha, BlendFactor::One );
+    let under_pipeline = ScreenQuad::pipeline( gfx, &FRAG_UNDER )
+    .with_texture_location( 0, "u_layer" )
+    .with_color_blend( under )
+    .with_alpha_blend( under )
+    .build().map_err( Error::pipeline )?;
+    let resolve_pipeline = ScreenQuad::pipeline( gfx, &FRAG_RESOLVE )
+    .with_texture_location( 0, "u_front" )
+    .with_color_blend( BlendMode::OVER )
+    .with_alpha_blend( BlendMode::OVER )
+    .build().map_err( Error::pipeline )?;
+
+    let quad = ScreenQuad::new( gfx )?;
+
+    Ok( Self
+    {
+      layers,
+      layer,
+      depths,
+      front,
+      occluders,
+      color_pipeline,
+      depth_pipeline,
+      under_pipeline,
+      resolve_pipeline,
+      quad,
+    })
+  }
+
+  /// Recreate the targets at the size of the window.
+  pub fn resize( &mut self, gfx : &mut Graphics ) -> Result< (), Error >
+  {
+    self.layer = Self::target( gfx, true )?;
+    self.depths = [ Self::target( gfx, true )?, Self::target( gfx, true )? ];
+    self.front = Self::target( gfx, false )?;
+    Ok( () )
+  }
+
+  fn target( gfx : &mut Graphics, depth : bool ) -> Result< RenderTexture, Error >
+  {
+    let ( width, height ) = gfx.size();
+    let builder = gfx.create_render_texture( width, height );
+    let builder = if depth { builder.with_depth() } else { builder };
+    builder.build().map_err( Error::texture )
+  }
+
+  /// Blend the translucent meshes over the screen, occluded by the opaque and alpha-tested ones.
+  ///
+  /// Expects the camera and the transformations of the meshes to be uploaded.
+  pub fn render( &self, gfx : &mut Graphics, meshes : &[ Mesh ], view : &View< '_ > )
+  {
+    // A packed depth of 0 lets every fragment through the first layer.
+    Self::clear( gfx, &self.depths[ 1 ] );
+    Self::clear( gfx, &self.front );
+
+    for index in 0..self.layers
+    {
+      let previous = &self.depths[ ( index + 1 ) % 2 ];
+      let current = &self.depths[ index % 2 ];
+
+      let layer = self.peel_renderer( gfx, meshes, view, previous, &self.color_pipeline, Color::TRANSPARENT );
+      gfx.render_to( &self.layer, &layer );
+
+      // A packed depth above 1 stops the next layers where this one found nothing.
+      let depth = self.peel_renderer( gfx, meshes, view, previous, &self.depth_pipeline, Color::WHITE );
+      gfx.render_to( current, &depth );
+
+      let mut under = gfx.create_renderer();
+      under.begin( None );
+      under.set_pipeline( &self.under_pipeline );
+      under.bind_texture( 0, &self.layer );
+      self.quad.draw( &mut under );
+      under.end();
+      gfx.render_to( &self.front, &under );
+    }
+
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( None );
+    renderer.set_pipeline( &self.resolve_pipeline );
+    renderer.bind_texture( 0, &self.front );
+    self.quad.draw( &mut renderer );
+    renderer.end();
+    gfx.render( &renderer );
+  }
+
+  fn clear( gfx : &mut Graphics, target : &RenderTexture )
+  {
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( Some( &ClearOptions::color( Color::TRANSPARENT ) ) );
+    renderer.end();
+    gfx.render_to( target, &renderer );
+  }
+
+  /// Lay down the depth of the meshes that occlude, then keep the nearest translucent fragments behind the previous layer.
+  fn peel_renderer( &self, gfx : &Graphics, meshes : &[ Mesh ], view : &View< '_ >, previous : &RenderTexture, pipeline : &Pipeline, clear : Color ) -> Renderer
+  {
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( Some( &ClearOptions
+    {
+      color : Some( clear ),
+      depth : Some( 1.0 ),
+      stencil : None,
+    }));
+
+    self.occluders.draw( &mut renderer, meshes, view );
+    renderer.set_pipeline( pipeline );
+    renderer.bind_texture_slot( 1, 1, previous );
+    for mesh in meshes.iter().filter( | mesh | mesh.class() == RenderClass::Translucent )
+    {
+      mesh.draw( &mut renderer );
+    }
+
+    renderer.end();
+    renderer
+  }
+}
@@ -0,0 +1,62 @@
+//!
+//! Ordering of translucent meshes by view-space depth.
+//!
+
+use notan::math::{ Mat4, Vec3 };
+
+/// Depth of the origin of a mesh in view space.
+///
+/// View space is right-handed with the viewer looking down `-z`, so the returned value is the
+/// distance in front of the viewer : bigger is farther.
+pub fn view_depth( view : &Mat4, transformations : &Mat4 ) -> f32
+{
+  -( *view * *transformations ).transform_point3( Vec3::ZERO ).z
+}
+
+/// Indices of the meshes in the order they should be drawn so that farther meshes come first.
+///
+/// The sort is stable : meshes at the same depth keep their relative order.
+pub fn back_to_front< 'a >( view : &Mat4, transformations : impl IntoIterator< Item = &'a Mat4 > ) -> Vec< usize >
+{
+  let mut order : Vec< ( usize, f32 ) > = transformations
+  .into_iter()
+  .map( | transformations | view_depth( view, transformations ) )
+  .enumerate()
+  .collect();
+
+  order.sort_by( | ( _, a ), ( _, b ) | b.total_cmp( a ) );
+  order.into_iter().map( | ( index, _ ) | index ).collect()
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn at( z : f32 ) -> Mat4
+  {
+    Mat4::from_translation( Vec3::new( 0.0, 0.0, z ) )
+  }
+
+  #[ test ]
+  fn view_depth_is_distance_in_front()
+  {
+    let view = Mat4::look_at_rh( Vec3::new( 0.0, 0.0, 5.0 ), Vec3::ZERO, Vec3::Y );
+    assert!( ( view_depth( &view, &at( 0.0 ) ) - 5.0 ).abs() < 1e-5 );
+    assert!( ( view_depth( &view, &at( 2.0 ) ) - 3.0 ).abs() < 1e-5 );
+  }
+
+  #[ test ]
+  fn farther_first()
+  {
+    let view = Mat4::look_at_rh( Vec3::new( 0.0, 0.0, 5.0 ), Vec3::ZERO, Vec3::Y );
+    assert_eq!( back_to_front( &view, [ &at( 1.0 ), &at( -1.0 ), &at( 0.0 ) ] ), vec![ 1, 2, 0 ] );
+  }
+
+  #[ test ]
+  fn ties_keep_their_order()
+  {
+    let ( near, far ) = ( at( 1.0 ), at( -1.0 ) );
+    assert_eq!( back_to_front( &Mat4::IDENTITY, [ &near, &far, &near, &far ] ), vec![ 1, 3, 0, 2 ] );
+  }
+}
@@ -0,0 +1,212 @@
+//!
+//! Draws of a frame recorded into a single renderer with as few state changes and draw calls as the
+//! order allows.
+//!
+
+use crate::geometry::Shape;
+
+/// Draw of one mesh : the pipeline, texture and geometry it needs, and the index of the mesh.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub struct Draw
+{
+  /// Index of the pipeline among the pipelines of the scene.
+  pub pipeline : usize,
+  /// Id of the texture, `None` for a draw without one.
+  pub texture : Option< u64 >,
+  pub shape : Shape,
+  pub mesh : usize,
+}
+
+/// Command recorded for a draw list.
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+pub enum Step
+{
+  /// Set the pipeline and the view it draws with.
+  Pipeline( usize ),
+  /// Bind the texture `id` of the mesh drawn next.
+  Texture
+  {
+    id : u64,
+    mesh : usize,
+  },
+  /// Draw instances of the meshes in one call, they share the pipeline, texture and shape.
+  Draw( Vec< usize > ),
+}
+
+/// Counters of what a frame recorded, to keep an eye on state changes.
+#[ derive( Debug, Default, Clone, Copy, PartialEq, Eq ) ]
+pub struct FrameStats
+{
+  pub draw_calls : usize,
+  pub instances : usize,
+  pub pipeline_changes : usize,
+  pub texture_changes : usize,
+}
+
+/// Draws of a frame in submission order.
+#[ derive( Debug, Default, Clone, PartialEq, Eq ) ]
+pub struct DrawList
+{
+  draws : Vec< Draw >,
+}
+
+impl DrawList
+{
+  /// Append draws whose order does not matter, like the ones the depth test resolves, grouped by
+  /// pipeline, texture then shape.
+  pub fn extend_unordered( &mut self, draws : impl IntoIterator< Item = Draw > )
+  {
+    let start = self.draws.len();
+    self.draws.extend( draws );
+    self.draws[ start.. ].sort_by_key( | draw | ( draw.pipeline, draw.texture, draw.shape ) );
+  }
+
+  /// Append draws that must keep their order, like translucent meshes sorted back to front.
+  pub fn extend_ordered( &mut self, draws : impl IntoIterator< Item = Draw > )
+  {
+    self.draws.extend( draws );
+  }
+
+  pub fn draws( &self ) -> &[ Draw ]
+  {
+    &self.draws
+  }
+
+  /// Commands recording the draws, skipping the pipelines and textures already set and drawing
+  /// consecutive meshes that share them and their shape in one call.
+  ///
+  /// Textures are bound again after a pipeline change, samplers belong to the program.
+  pub fn steps( &self ) -> Vec< Step >
+  {
+    let mut steps = Vec::with_capacity( self.draws.len() * 3 );
+    let mut pipeline = None;
+    let mut texture = None;
+    let mut shape = None;
+
+    for draw in &self.draws
+    {
+      let mut batched = shape == Some( draw.shape );
+      shape = Some( draw.shape );
+      if pipeline != Some( draw.pipeline )
+      {
+        pipeline = Some( draw.pipeline );
+        texture = None;
+        batched = false;
+        steps.push( Step::Pipeline( draw.pipeline ) );
+      }
+      if texture != draw.texture
+      {
+        texture = draw.texture;
+        batched = false;
+        if let Some( id ) = draw.texture
+        {
+          steps.push( Step::Texture { id, mesh : draw.mesh } );
+        }
+      }
+
+      match steps.last_mut()
+      {
+        Some( Step::Draw( meshes ) ) if batched => meshes.push( draw.mesh ),
+        _ => steps.push( Step::Draw( vec![ draw.mesh ] ) ),
+      }
+    }
+
+    steps
+  }
+
+  /// What recording the draws costs.
+  pub fn stats( &self ) -> FrameStats
+  {
+    self.steps().iter().fold( FrameStats::default(), | mut stats, step |
+    {
+      match step
+      {
+        Step::Pipeline( _ ) => stats.pipeline_changes += 1,
+        Step::Texture { .. } => stats.texture_changes += 1,
+        Step::Draw( meshes ) =>
+        {
+          stats.draw_calls += 1;
+          stats.instances += meshes.len();
+        }
+      }
+      stats
+    })
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn draw( pipeline : usize, texture : Option< u64 >, mesh : usize ) -> Draw
+  {
+    Draw { pipeline, texture, shape : Shape::Quad, mesh }
+  }
+
+  #[ test ]
+  fn unordered_groups_by_pipeline_then_texture()
+  {
+    let mut list = DrawList::default();
+    list.extend_unordered( [ draw( 1, Some( 7 ), 0 ), draw( 0, Some( 8 ), 1 ), draw( 0, Some( 7 ), 2 ), draw( 1, Some( 7 ), 3 ) ] );
+    let meshes : Vec< usize > = list.draws().iter().map( | draw | draw.mesh ).collect();
+    assert_eq!( meshes, vec![ 2, 1, 0, 3 ] );
+  }
+
+  #[ test ]
+  fn ordered_keeps_the_order_after_unordered()
+  {
+    let mut list = DrawList::default();
+    list.extend_unordered( [ draw( 1, Some( 7 ), 0 ), draw( 0, Some( 7 ), 1 ) ] );
+    list.extend_ordered( [ draw( 2, Some( 8 ), 4 ), draw( 2, Some( 7 ), 3 ), draw( 2, Some( 8 ), 2 ) ] );
+    let meshes : Vec< usize > = list.draws().iter().map( | draw | draw.mesh ).collect();
+    assert_eq!( meshes, vec![ 1, 0, 4, 3, 2 ] );
+  }
+
+  #[ test ]
+  fn shared_pipeline_and_texture_draw_in_one_call()
+  {
+    let mut list = DrawList::default();
+    list.extend_unordered( [ draw( 0, Some( 7 ), 0 ), draw( 0, Some( 8 ), 1 ), draw( 0, Some( 7 ), 2 ), draw( 1, Some( 7 ), 3 ) ] );
+    assert_eq!( list.steps(), vec!
+    [
+      Step::Pipeline( 0 ),
+      Step::Texture { id : 7, mesh : 0 },
+      Step::Draw( vec![ 0, 2 ] ),
+      Step::Texture { id : 8, mesh : 1 },
+      Step::Draw( vec![ 1 ] ),
+      Step::Pipeline( 1 ),
+      Step::Texture { id : 7, mesh : 3 },
+      Step::Draw( vec![ 3 ] ),
+    ]);
+    assert_eq!( list.stats(), FrameStats { draw_calls : 3, instances : 4, pipeline_changes : 2, texture_changes : 3 } );
+  }
+
+  #[ test ]
+  fn ordered_draws_alternating_textures_are_not_batched()
+  {
+    let mut list = DrawList::default();
+    list.extend_ordered( [ draw( 2, Some( 7 ), 0 ), draw( 2, Some( 8 ), 1 ), draw( 2, Some( 7 ), 2 ) ] );
+    assert_eq!( list.stats(), FrameStats { draw_calls : 3, instances : 3, pipeline_changes : 1, texture_changes : 3 } );
+  }
+
+  #[ test ]
+  fn loading_textures_batch_without_bind()
+  {
+    let mut list = DrawList::default();
+    list.extend_unordered( [ draw( 0, None, 0 ), draw( 0, None, 1 ), draw( 0, Some( 7 ), 2 ) ] );
+    assert_eq!( list.steps(), vec!
+    [
+      Step::Pipeline( 0 ),
+      Step::Draw( vec![ 0, 1 ] ),
+      Step::Texture { id : 7, mesh : 2 },
+      Step::Draw( vec![ 2 ] ),
+    ]);
+  }
+
+  #[ test ]
+  fn empty_list_costs_nothing()
+  {
+    assert_eq!( DrawList::default().stats(), FrameStats::default() );
+  }
+}
@@ -0,0 +1,82 @@
+//!
+//! Errors of the construction of scenes and meshes.
+//!
+
+use std::fmt;
+
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+pub enum Error
+{
+  /// The asset at `path` could not be loaded.
+  Asset
+  {
+    path : String,
+    message : String,
+  },
+  /// A vertex, index or uniform buffer could not be created.
+  Buffer( String ),
+  /// A texture or an offscreen render target could not be created.
+  Texture( String ),
+  /// A pipeline could not be created : a shader did not compile or the program did not link,
+  /// the message of the backend tells which.
+  Pipeline( String ),
+  /// The images of an atlas could not be packed.
+  Atlas( String ),
+  /// A value of the scene configuration is out of range.
+  Config( String ),
+  /// A scene file or a manifest is not valid JSON of its type.
+  Parse( String ),
+  /// The field of a mesh of a scene file has an invalid value.
+  Invalid
+  {
+    mesh : usize,
+    field : &'static str,
+    message : String,
+  },
+  /// The field of an asset of a manifest has an invalid value.
+  InvalidAsset
+  {
+    asset : usize,
+    field : &'static str,
+    message : String,
+  },
+}
+
+impl Error
+{
+  pub( crate ) fn buffer( message : String ) -> Self
+  {
+    Error::Buffer( message )
+  }
+
+  pub( crate ) fn texture( message : String ) -> Self
+  {
+    Error::Texture( message )
+  }
+
+  pub( crate ) fn pipeline( message : String ) -> Self
+  {
+    Error::Pipeline( message )
+  }
+}
+
+impl fmt::Display for Error
+{
+  fn fmt( &self, f : &mut fmt::Formatter< '_ > ) -> fmt::Result
+  {
+    match self
+    {
+      Error::Asset { path, message } => write!( f, "can not load asset {path} : {message}" ),
+      Error::Buffer( message ) => write!( f, "can not create buffer : {message}" ),
+      Error::Texture( message ) => write!( f, "can not create texture : {message}" ),
+      Error::Pipeline( message ) => write!( f, "can not create pipeline : {message}" ),
+      Error::Atlas( message ) => write!( f, "can not pack atlas : {message}" ),
+      Error::Config( message ) => write!( f, "invalid scene config : {message}" ),
+      Error::Parse( message ) => write!( f, "invalid JSON : {message}" ),
+      Error::Invalid { mesh, field, message } => write!( f, "invalid scene file : mesh {mesh}, {field} {message}" ),
+      Error::InvalidAsset { asset, field, message } => write!( f, "invalid manifest : asset {asset}, {field} {message}" ),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
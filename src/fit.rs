@@ -0,0 +1,48 @@
+//!
+//! Placement of the scene in a window whose aspect ratio differs from the one of the scene.
+//!
+
+/// Rectangle of the window the scene is drawn in, from the top left corner in window units.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Viewport
+{
+  pub x : f32,
+  pub y : f32,
+  pub width : f32,
+  pub height : f32,
+}
+
+/// How the scene fits a window with another aspect ratio.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum FitMode
+{
+  /// Covers the window, distorted.
+  Stretch,
+  /// Largest centered rectangle of the window with the aspect ratio of the scene, bars fill the rest.
+  Letterbox,
+  /// Smallest centered rectangle covering the window with the aspect ratio of the scene, the
+  /// overflowing sides are cut.
+  Fill,
+}
+
+impl FitMode
+{
+  /// Viewport of a scene `aspect` times wider than high in a window of `width` by `height`.
+  pub fn viewport( self, aspect : f32, width : f32, height : f32 ) -> Viewport
+  {
+    let ( fit_width, fit_height ) = match self
+    {
+      FitMode::Stretch => ( width, height ),
+      FitMode::Letterbox => ( width.min( height * aspect ), height.min( width / aspect ) ),
+      FitMode::Fill => ( width.max( height * aspect ), height.max( width / aspect ) ),
+    };
+
+    Viewport
+    {
+      x : ( width - fit_width ) * 0.5,
+      y : ( height - fit_height ) * 0.5,
+      width : fit_width,
+      height : fit_height,
+    }
+  }
+}
@@ -0,0 +1,127 @@
+//!
+//! Geometry shared by meshes, uploaded once per shape.
+//!
+
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::rc::{ Rc, Weak };
+use notan::prelude::*;
+use crate::cache::RefCache;
+use crate::error::Error;
+
+/// Geometry meshes can share.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash ) ]
+pub enum Shape
+{
+  /// Square from -1 to 1 in xy at z = 0, with uvs from 0 to 1. Uvs start at the top left of the
+  /// texture, u to the right and v downward, and +y is up : v is 0 at y = 1.
+  Quad,
+}
+
+/// Buffers of a shape, clones share the same GPU buffers.
+#[ derive( Debug, Clone ) ]
+pub struct Geometry
+{
+  pub shape : Shape,
+  pub vertex_buffer : Buffer,
+  pub index_buffer : Buffer,
+  /// Number of indices.
+  pub count : i32,
+}
+
+impl Geometry
+{
+  /// Upload the geometry of `shape`.
+  pub fn new( gfx : &mut Graphics, shape : Shape ) -> Result< Self, Error >
+  {
+    let vertex_info = VertexInfo::new()
+    .attr( 0, VertexFormat::Float32x3 ) // positions
+    .attr( 1, VertexFormat::Float32x2 ); // uvs
+
+    let ( vertices, indices ) = match shape
+    {
+      Shape::Quad =>
+      (
+        [
+        -1.0,  -1.0,  0.0,    0.0, 1.0,
+        -1.0,   1.0,  0.0,    0.0, 0.0,
+        1.0,   1.0,  0.0,    1.0, 0.0,
+        1.0,  -1.0,  0.0,    1.0, 1.0,
+        ],
+        [ 0, 1, 2, 2, 3, 0 ],
+      ),
+    };
+
+    let vertex_buffer = gfx
+    .create_vertex_buffer()
+    .with_info( &vertex_info )
+    .with_data( &vertices )
+    .build().map_err( Error::buffer )?;
+    let index_buffer = gfx
+    .create_index_buffer()
+    .with_data( &indices )
+    .build().map_err( Error::buffer )?;
+
+    Ok( Self
+    {
+      shape,
+      vertex_buffer,
+      index_buffer,
+      count : indices.len() as i32,
+    })
+  }
+}
+
+/// Geometry of the meshes of a scene by shape. The GPU buffers of a shape are freed once every
+/// mesh using them was dropped.
+#[ derive( Debug, Default ) ]
+pub struct GeometryCache
+{
+  cache : Rc< RefCell< RefCache< Shape, Geometry > > >,
+}
+
+impl GeometryCache
+{
+  /// Geometry of `shape`, uploaded if no mesh uses it yet. Dropping the handle stops using it.
+  pub fn acquire( &mut self, gfx : &mut Graphics, shape : Shape ) -> Result< SharedGeometry, Error >
+  {
+    let geometry = self.cache.borrow_mut().try_acquire( shape, || Geometry::new( gfx, shape ) )?;
+    Ok( SharedGeometry { geometry, cache : Rc::downgrade( &self.cache ) } )
+  }
+
+  /// Number of meshes using `shape`.
+  pub fn users( &self, shape : Shape ) -> usize
+  {
+    self.cache.borrow().users( &shape )
+  }
+}
+
+/// Geometry used by a mesh, released from its cache on drop.
+#[ derive( Debug ) ]
+pub struct SharedGeometry
+{
+  geometry : Geometry,
+  cache : Weak< RefCell< RefCache< Shape, Geometry > > >,
+}
+
+impl Deref for SharedGeometry
+{
+  type Target = Geometry;
+
+  fn deref( &self ) -> &Geometry
+  {
+    &self.geometry
+  }
+}
+
+impl Drop for SharedGeometry
+{
+  fn drop( &mut self )
+  {
+    // The cache may be dropped first, its buffers then go with the last geometry.
+    if let Some( cache ) = self.cache.upgrade()
+    {
+      cache.borrow_mut().release( &self.geometry.shape );
+    }
+  }
+}
@@ -0,0 +1,308 @@
+//!
+//! Parent-child relationships between the meshes of a scene.
+//!
+
+use notan::math::Mat4;
+
+/// Tree of nodes indexed like the meshes of a scene, a node without parent is a root.
+#[ derive( Debug, Clone, Default, PartialEq, Eq ) ]
+pub struct Graph
+{
+  parents : Vec< Option< usize > >,
+}
+
+impl Graph
+{
+  /// Number of nodes.
+  pub fn len( &self ) -> usize
+  {
+    self.parents.len()
+  }
+
+  pub fn is_empty( &self ) -> bool
+  {
+    self.parents.is_empty()
+  }
+
+  /// Add a root node, its index is the number of nodes before.
+  pub fn push( &mut self ) -> usize
+  {
+    self.parents.push( None );
+    self.parents.len() - 1
+  }
+
+  /// Remove a node, its children become roots and the nodes after it move down by one index.
+  pub fn remove( &mut self, index : usize )
+  {
+    self.parents.remove( index );
+    for parent in &mut self.parents
+    {
+      *parent = match *parent
+      {
+        Some( parent ) if parent == index => None,
+        Some( parent ) if parent > index => Some( parent - 1 ),
+        parent => parent,
+      };
+    }
+  }
+
+  pub fn parent( &self, index : usize ) -> Option< usize >
+  {
+    self.parents[ index ]
+  }
+
+  /// Direct children of a node.
+  pub fn children( &self, index : usize ) -> impl Iterator< Item = usize > + '_
+  {
+    self.parents
+    .iter()
+    .enumerate()
+    .filter( move | ( _, parent ) | **parent == Some( index ) )
+    .map( | ( child, _ ) | child )
+  }
+
+  /// Whether `ancestor` is `index` or one of its ancestors.
+  pub fn is_ancestor( &self, ancestor : usize, index : usize ) -> bool
+  {
+    let mut node = Some( index );
+    while let Some( current ) = node
+    {
+      if current == ancestor
+      {
+        return true;
+      }
+      node = self.parents[ current ];
+    }
+    false
+  }
+
+  /// Attach `index` to `parent`, or make it a root. Returns `false` and changes nothing if `parent`
+  /// is `index` or one of its descendants.
+  pub fn set_parent( &mut self, index : usize, parent : Option< usize > ) -> bool
+  {
+    if let Some( parent ) = parent
+    {
+      if self.is_ancestor( index, parent )
+      {
+        return false;
+      }
+    }
+    self.parents[ index ] = parent;
+    true
+  }
+
+  /// World matrices of the nodes from their matrices relative to their parent.
+  pub fn propagate( &self, locals : &[ Mat4 ] ) -> Vec< Mat4 >
+  {
+    let mut worlds : Vec< Option< Mat4 > > = vec![ None; self.parents.len() ];
+    let mut chain = vec![];
+    for index in 0..self.parents.len()
+    {
+      // Walk up to the first node whose world is known, then compute the worlds back down.
+      let mut node = Some( index );
+      let mut world = Mat4::IDENTITY;
+      while let Some( current ) = node
+      {
+        if let Some( known ) = worlds[ current ]
+        {
+          world = known;
+          break;
+        }
+        chain.push( current );
+        node = self.parents[ current ];
+      }
+      while let Some( current ) = chain.pop()
+      {
+        world *= locals[ current ];
+        worlds[ current ] = Some( world );
+      }
+    }
+    worlds.into_iter().map( Option::unwrap ).collect()
+  }
+
+  /// Recompute in `worlds` the world matrices of the `dirty` nodes and their descendants, `local`
+  /// giving the matrix of a node relative to its parent. The other world matrices are kept.
+  /// Returns which nodes were recomputed.
+  pub fn propagate_dirty( &self, dirty : &[ bool ], local : impl Fn( usize ) -> Mat4, worlds : &mut [ Mat4 ] ) -> Vec< bool >
+  {
+    // A node is stale if it or an ancestor is dirty, known once the walk up reaches a known node.
+    let mut stale : Vec< Option< bool > > = vec![ None; self.parents.len() ];
+    let mut chain = vec![];
+    for index in 0..self.parents.len()
+    {
+      let mut node = Some( index );
+      let mut above = false;
+      while let Some( current ) = node
+      {
+        if let Some( known ) = stale[ current ]
+        {
+          above = known;
+          break;
+        }
+        chain.push( current );
+        node = self.parents[ current ];
+      }
+      while let Some( current ) = chain.pop()
+      {
+        above |= dirty[ current ];
+        stale[ current ] = Some( above );
+      }
+    }
+    let stale : Vec< bool > = stale.into_iter().map( Option::unwrap ).collect();
+
+    // Then the same walk as `propagate`, from the first node up that is kept or already recomputed.
+    let mut done = vec![ false; self.parents.len() ];
+    for index in ( 0..self.parents.len() ).filter( | &index | stale[ index ] )
+    {
+      let mut node = Some( index );
+      let mut world = Mat4::IDENTITY;
+      while let Some( current ) = node
+      {
+        if done[ current ] || !stale[ current ]
+        {
+          world = worlds[ current ];
+          break;
+        }
+        chain.push( current );
+        node = self.parents[ current ];
+      }
+      while let Some( current ) = chain.pop()
+      {
+        world *= local( current );
+        worlds[ current ] = world;
+        done[ current ] = true;
+      }
+    }
+    stale
+  }
+}
+
+/// Matrix relative to a parent with the world matrix `parent`, or to the world without parent,
+/// that keeps the world matrix `world`. `None` if the parent is scaled to zero : no matrix under
+/// it gives back `world`, or if `world` is scaled to zero and does not decompose into a transform.
+pub fn keep_world( world : Mat4, parent : Option< Mat4 > ) -> Option< Mat4 >
+{
+  if world.determinant() == 0.0
+  {
+    return None;
+  }
+  match parent
+  {
+    Some( parent ) if parent.determinant() == 0.0 => None,
+    Some( parent ) => Some( parent.inverse() * world ),
+    None => Some( world ),
+  }
+  .filter( | local |
+  {
+    let ( scale, rotation, translation ) = local.to_scale_rotation_translation();
+    scale.is_finite() && rotation.is_finite() && translation.is_finite()
+  })
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+  use notan::math::Vec3;
+
+  fn at( x : f32 ) -> Mat4
+  {
+    Mat4::from_translation( Vec3::new( x, 0.0, 0.0 ) )
+  }
+
+  /// 0 <- 1 <- 2 and the root 3.
+  fn chain() -> Graph
+  {
+    let mut graph = Graph::default();
+    for _ in 0..4
+    {
+      graph.push();
+    }
+    assert!( graph.set_parent( 1, Some( 0 ) ) );
+    assert!( graph.set_parent( 2, Some( 1 ) ) );
+    graph
+  }
+
+  #[ test ]
+  fn propagates_through_a_chain()
+  {
+    let graph = chain();
+    let worlds = graph.propagate( &[ at( 1.0 ), Mat4::from_scale( Vec3::splat( 2.0 ) ), at( 3.0 ), at( 4.0 ) ] );
+    let x = | index : usize | worlds[ index ].w_axis.x;
+    assert_eq!( ( x( 0 ), x( 1 ), x( 2 ), x( 3 ) ), ( 1.0, 1.0, 7.0, 4.0 ) );
+  }
+
+  #[ test ]
+  fn cycles_are_refused()
+  {
+    let mut graph = chain();
+    assert!( !graph.set_parent( 0, Some( 2 ) ) );
+    assert!( !graph.set_parent( 1, Some( 1 ) ) );
+    assert_eq!( graph, chain() );
+  }
+
+  #[ test ]
+  fn reparenting_keeps_the_world()
+  {
+    let parent = Mat4::from_scale_rotation_translation( Vec3::new( 2.0, 1.0, 0.5 ), notan::math::Quat::from_rotation_z( 0.5 ), Vec3::new( 1.0, 2.0, 3.0 ) );
+    let world = at( 5.0 );
+    let local = keep_world( world, Some( parent ) ).unwrap();
+    assert!( ( parent * local ).abs_diff_eq( world, 1e-5 ) );
+    assert_eq!( keep_world( world, None ), Some( world ) );
+  }
+
+  #[ test ]
+  fn parent_scaled_to_zero_has_no_local()
+  {
+    assert_eq!( keep_world( at( 5.0 ), Some( Mat4::from_scale( Vec3::new( 1.0, 0.0, 1.0 ) ) ) ), None );
+  }
+
+  #[ test ]
+  fn world_scaled_to_zero_has_no_local()
+  {
+    let world = Mat4::from_scale_rotation_translation( Vec3::new( 1.0, 0.0, 1.0 ), notan::math::Quat::IDENTITY, Vec3::new( 5.0, 0.0, 0.0 ) );
+    assert_eq!( keep_world( world, Some( at( 1.0 ) ) ), None );
+    assert_eq!( keep_world( world, None ), None );
+    assert_eq!( keep_world( Mat4::from_scale( Vec3::new( f32::NAN, 1.0, 1.0 ) ), None ), None );
+  }
+
+  #[ test ]
+  fn removing_detaches_the_children()
+  {
+    let mut graph = chain();
+    graph.remove( 1 );
+    assert_eq!( graph.len(), 3 );
+    assert_eq!( ( graph.parent( 0 ), graph.parent( 1 ), graph.parent( 2 ) ), ( None, None, None ) );
+
+    let mut graph = chain();
+    graph.remove( 0 );
+    assert_eq!( ( graph.parent( 0 ), graph.parent( 1 ) ), ( None, Some( 0 ) ) );
+  }
+
+  #[ test ]
+  fn only_dirty_subtrees_are_recomputed()
+  {
+    let graph = chain();
+    let locals = [ at( 1.0 ), at( 2.0 ), at( 3.0 ), at( 4.0 ) ];
+    let mut worlds = graph.propagate( &locals );
+
+    // A stale world kept for a clean node shows it was not recomputed.
+    worlds[ 0 ] = at( 10.0 );
+    worlds[ 3 ] = at( 40.0 );
+    let changed = graph.propagate_dirty( &[ false, true, false, false ], | index | locals[ index ], &mut worlds );
+    assert_eq!( changed, vec![ false, true, true, false ] );
+    let x = | index : usize | worlds[ index ].w_axis.x;
+    assert_eq!( ( x( 0 ), x( 1 ), x( 2 ), x( 3 ) ), ( 10.0, 12.0, 15.0, 40.0 ) );
+  }
+
+  #[ test ]
+  fn all_dirty_matches_propagate()
+  {
+    let graph = chain();
+    let locals = [ at( 1.0 ), at( 2.0 ), at( 3.0 ), at( 4.0 ) ];
+    let mut worlds = vec![ Mat4::IDENTITY; 4 ];
+    let changed = graph.propagate_dirty( &[ true; 4 ], | index | locals[ index ], &mut worlds );
+    assert_eq!( changed, vec![ true; 4 ] );
+    assert_eq!( worlds, graph.propagate( &locals ) );
+  }
+}
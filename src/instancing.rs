@@ -0,0 +1,219 @@
+//!
+//! Instanced drawing of meshes, one draw per run of meshes sharing a pipeline and a texture.
+//!
+
+use notan::prelude::*;
+use notan::log;
+use crate::draw_list::{ DrawList, FrameStats, Step };
+use crate::error::Error;
+use crate::lib::{ AlphaMode, Coverage, Mesh, View };
+
+const VERT_INSTANCED : ShaderSource< '_ > = notan::vertex_shader!
+{
+  r#"
+  #version 450
+
+  layout( location = 0 ) in vec3 a_pos;
+  layout( location = 1 ) in vec2 a_uv;
+  layout( location = 2 ) in vec4 a_model_0;
+  layout( location = 3 ) in vec4 a_model_1;
+  layout( location = 4 ) in vec4 a_model_2;
+  layout( location = 5 ) in vec4 a_model_3;
+  layout( location = 6 ) in vec4 a_uv_rect;
+  layout( location = 7 ) in vec4 a_tint;
+  layout( location = 8 ) in vec4 a_material;
+  layout( location = 0 ) out vec2 v_uv;
+  layout( location = 1 ) out vec4 v_color;
+  layout( location = 2 ) out vec4 v_material;
+
+  layout( set = 0, binding = 0 ) uniform Camera
+  {
+    mat4 view_projection;
+  };
+
+  void main()
+  {
+    v_uv = a_uv_rect.xy + a_uv * a_uv_rect.zw;
+    v_color = vec4( a_tint.rgb, a_tint.a * a_material.x );
+    v_material = a_material;
+    mat4 model = mat4( a_model_0, a_model_1, a_model_2, a_model_3 );
+    gl_Position = view_projection * model * vec4( a_pos, 1.0 );
+  }
+  "#
+};
+
+/// Same as `FRAG` with the material of the instance : its opacity is in the alpha of `v_color`, then premultiplied and cutoff.
+pub( crate ) const FRAG_INSTANCED : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 1 ) in vec4 v_color;
+  layout( location = 2 ) in vec4 v_material;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+
+  void main()
+  {
+    color = texture( u_texture, v_uv ) * vec4( v_color.rgb * mix( 1.0, v_color.a, v_material.y ), v_color.a );
+    if( color.a < v_material.z )
+    {
+      discard;
+    }
+  }
+  "#
+};
+
+/// Floats per instance : model matrix, uv rectangle, tint and material.
+pub( crate ) const INSTANCE_FLOATS : usize = 28;
+
+fn instance_info() -> VertexInfo
+{
+  VertexInfo::new()
+  .attr( 2, VertexFormat::Float32x4 ) // model columns
+  .attr( 3, VertexFormat::Float32x4 )
+  .attr( 4, VertexFormat::Float32x4 )
+  .attr( 5, VertexFormat::Float32x4 )
+  .attr( 6, VertexFormat::Float32x4 ) // uv rectangle
+  .attr( 7, VertexFormat::Float32x4 ) // tint
+  .attr( 8, VertexFormat::Float32x4 ) // opacity, premultiplied, cutoff
+  .step_mode( VertexStepMode::Instance )
+}
+
+/// Pipeline drawing instances of meshes with the given fragment shader, blending and depth are left to the caller.
+fn instanced_pipeline< 'a >( gfx : &'a mut Graphics, fragment : &'a ShaderSource< 'a > ) -> PipelineBuilder< 'a, 'a >
+{
+  let vertex_info = VertexInfo::new()
+  .attr( 0, VertexFormat::Float32x3 ) // positions
+  .attr( 1, VertexFormat::Float32x2 ); // uvs
+
+  gfx
+  .create_pipeline()
+  .from( &VERT_INSTANCED, fragment )
+  .with_vertex_info( &vertex_info )
+  .with_vertex_info( &instance_info() )
+  .with_texture_location( 0, "u_texture" )
+}
+
+/// Draws meshes in a single renderer, one instanced draw per run of meshes sharing a pipeline and
+/// a texture.
+///
+/// WebGL 2 can not start instanced draws past the first instance of a buffer, so every draw of a
+/// frame gets its own instance buffer, uploaded before the renderer is submitted.
+#[ derive( Debug ) ]
+pub( crate ) struct Instanced
+{
+  /// Pipelines of the meshes, indexed by `RenderClass::pipeline_index`.
+  pipelines : [ Pipeline; 3 ],
+  buffers : Vec< Buffer >,
+}
+
+impl Instanced
+{
+  pub fn new( gfx : &mut Graphics, coverage : Coverage, alpha_mode : AlphaMode ) -> Result< Self, Error >
+  {
+    let depth_write = DepthStencil
+    {
+      write : true,
+      compare : CompareMode::Less,
+    };
+    let depth_test = DepthStencil
+    {
+      write : false,
+      compare : CompareMode::Less,
+    };
+
+    let opaque_pipeline = instanced_pipeline( gfx, &FRAG_INSTANCED )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    let alpha_tested_pipeline = instanced_pipeline( gfx, coverage.instanced_fragment( alpha_mode ) )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    let translucent_pipeline = match alpha_mode
+    {
+      AlphaMode::Straight => instanced_pipeline( gfx, &FRAG_INSTANCED )
+      .with_color_blend( BlendMode::NORMAL ),
+      AlphaMode::Premultiplied => instanced_pipeline( gfx, &FRAG_INSTANCED )
+      .with_color_blend( BlendMode::OVER )
+      .with_alpha_blend( BlendMode::OVER ),
+    }
+    .with_depth_stencil( depth_test )
+    .build().map_err( Error::pipeline )?;
+
+    Ok( Self
+    {
+      pipelines : [ opaque_pipeline, alpha_tested_pipeline, translucent_pipeline ],
+      buffers : vec![],
+    })
+  }
+
+  /// Clear the screen and draw the meshes in the order of the list.
+  pub fn render( &mut self, gfx : &mut Graphics, meshes : &[ Mesh ], list : &DrawList, view : &View< '_ >, clear : &ClearOptions ) -> FrameStats
+  {
+    let steps = list.steps();
+    let batches = steps.iter().filter_map( | step | match step
+    {
+      Step::Draw( meshes ) => Some( meshes ),
+      _ => None,
+    });
+    let uploaded : Vec< bool > = batches
+    .enumerate()
+    .map( | ( index, batch ) |
+    {
+      let instances : Vec< f32 > = batch.iter().flat_map( | &mesh | meshes[ mesh ].instance() ).collect();
+      self.upload( gfx, index, &instances )
+      .map_err( | error | log::error!( "{error}, {} meshes are not drawn", batch.len() ) )
+      .is_ok()
+    })
+    .collect();
+
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( Some( clear ) );
+    let mut index = 0;
+    for step in steps
+    {
+      match step
+      {
+        Step::Pipeline( pipeline ) =>
+        {
+          renderer.set_pipeline( &self.pipelines[ pipeline ] );
+          view.bind( &mut renderer );
+        }
+        Step::Texture { mesh, .. } => meshes[ mesh ].bind_texture( &mut renderer ),
+        Step::Draw( batch ) =>
+        {
+          if uploaded[ index ]
+          {
+            // The meshes of a draw share their shape.
+            let geometry = meshes[ batch[ 0 ] ].geometry();
+            renderer.bind_buffers( &[ &geometry.vertex_buffer, &geometry.index_buffer, &self.buffers[ index ] ] );
+            renderer.draw_instanced( 0, geometry.count, batch.len() as i32 );
+          }
+          index += 1;
+        }
+      }
+    }
+    renderer.end();
+    gfx.render( &renderer );
+
+    list.stats()
+  }
+
+  /// Upload the instances of the draw `index` of the frame.
+  fn upload( &mut self, gfx : &mut Graphics, index : usize, instances : &[ f32 ] ) -> Result< (), Error >
+  {
+    while self.buffers.len() <= index
+    {
+      let buffer = gfx
+      .create_vertex_buffer()
+      .with_info( &instance_info() )
+      .build().map_err( Error::buffer )?;
+      self.buffers.push( buffer );
+    }
+
+    gfx.set_buffer_data( &self.buffers[ index ], instances );
+    Ok( () )
+  }
+}
@@ -1,5 +1,30 @@
+use std::cell::{ Cell, RefCell };
+use std::collections::HashMap;
+use std::rc::Rc;
 use notan::prelude::*;
-use notan::math::{ Mat4, Quat, Vec3 };
+use notan::log;
+use notan::math::{ Mat4, Quat, Rect, Vec3 };
+use serde::{ Deserialize, Serialize };
+use crate::animation::{ Animation, AnimationEvent };
+use crate::atlas::Atlas;
+use crate::camera::Camera;
+use crate::clock::Clock;
+use crate::depth_sort::back_to_front;
+use crate::error::Error;
+use crate::draw_list::{ Draw, DrawList, FrameStats };
+use crate::fit::{ FitMode, Viewport };
+use crate::geometry::{ Geometry, GeometryCache, Shape, SharedGeometry };
+use crate::graph::{ keep_world, Graph };
+use crate::sprites::Sprites;
+use crate::sprite_batch::Sprite;
+use crate::transform::Transform;
+use crate::manifest::{ Loading, Manifest, Preload };
+use crate::placeholder::Placeholders;
+use crate::sampling::{ image_loader, ImageFile, Sampling };
+use crate::scene_file::{ MeshEntry, SceneFile };
+use crate::instancing::{ Instanced, FRAG_INSTANCED, INSTANCE_FLOATS };
+use crate::weighted_blended::WeightedBlended;
+use crate::depth_peeling::DepthPeeling;
 
 const VERT : ShaderSource< '_ > = notan::vertex_shader!
 {
@@ -9,16 +34,32 @@ const VERT : ShaderSource< '_ > = notan::vertex_shader!
   layout( location = 0 ) in vec3 a_pos;
   layout( location = 1 ) in vec2 a_uv;
   layout( location = 0 ) out vec2 v_uv;
+  layout( location = 1 ) out vec4 v_color;
+  
+  layout( set = 0, binding = 0 ) uniform Camera
+  {
+    mat4 view_projection;
+  };
   
   layout( set = 1, binding = 0 ) uniform MeshTransformations
   {
     mat4 model;
+    vec4 uv_rect;
+  };
+  
+  layout( set = 2, binding = 0 ) uniform MeshMaterial
+  {
+    vec4 tint;
+    float opacity;
+    float premultiplied;
+    float cutoff;
   };
   
   void main()
   {
-    v_uv = a_uv;
-    gl_Position = model * vec4( a_pos.x, a_pos.y * -1.0, a_pos.z, 1.0 );
+    v_uv = uv_rect.xy + a_uv * uv_rect.zw;
+    v_color = vec4( tint.rgb, tint.a * opacity );
+    gl_Position = view_projection * model * vec4( a_pos, 1.0 );
   }
   "#
 };
@@ -32,144 +73,1794 @@ const FRAG : ShaderSource< '_ > = notan::fragment_shader!
   layout( location = 0 ) in vec2 v_uv;
   layout( location = 0 ) out vec4 color;
   layout( location = 0 ) uniform sampler2D u_texture;
+  layout( set = 2, binding = 0 ) uniform MeshMaterial
+  {
+    vec4 tint;
+    float opacity;
+    float premultiplied;
+    float cutoff;
+  };
   
   void main()
   {
-    color = texture( u_texture, v_uv );
+    // Premultiplied texels are scaled by the whole alpha of the tint.
+    float alpha = tint.a * opacity;
+    color = texture( u_texture, v_uv ) * vec4( tint.rgb * mix( 1.0, alpha, premultiplied ), alpha );
+    if( color.a < cutoff )
+    {
+      discard;
+    }
   }
   "#
 };
 
-#[ derive( Debug ) ]
-pub struct Mesh
+/// Alpha-tested fragments dithered by alpha. The vertex shaders of the meshes, of the instances and
+/// of the sprites all pass it the uv and the tint with the opacity in its alpha.
+pub( crate ) const FRAG_DITHER : ShaderSource< '_ > = notan::fragment_shader!
 {
-  pub texture : Asset< Texture >,
-  pub vertext_buffer : Buffer,
-  pub index_buffer : Buffer,
-  pub transformations_buffer : Buffer,
-  pub transformations : Mat4,
-}
+  r#"
+  #version 450
+  precision mediump float;
+  
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 1 ) in vec4 v_color;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  
+  // Ordered dither thresholds of a 4x4 Bayer matrix, in 16ths.
+  const float BAYER[ 16 ] = float[]( 0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0 );
+  
+  void main()
+  {
+    color = texture( u_texture, v_uv ) * v_color;
+    ivec2 cell = ivec2( mod( gl_FragCoord.xy, 4.0 ) );
+    if( color.a <= ( BAYER[ cell.y * 4 + cell.x ] + 0.5 ) / 16.0 )
+    {
+      discard;
+    }
+    color.a = 1.0;
+  }
+  "#
+};
 
-impl Mesh
+/// Same as `FRAG_DITHER` for premultiplied textures : the fragments it keeps are opaque, so their
+/// texel is divided back by its alpha.
+pub( crate ) const FRAG_DITHER_PREMULTIPLIED : ShaderSource< '_ > = notan::fragment_shader!
 {
-  pub fn new( gfx: &mut Graphics, assets : &mut Assets, path : &'static str, scale : impl Into< Vec3 >, translation : impl Into< Vec3 > ) -> Self
-  {
-    let texture = assets.load_asset( path ).unwrap();
-    
-    let vertex_info = VertexInfo::new()
-    .attr( 0, VertexFormat::Float32x3 ) // positions
-    .attr( 1, VertexFormat::Float32x2 ); // uvs
-
-    let vertices =
-    [
-    -1.0,  -1.0,  0.0,    0.0, 0.0,
-    -1.0,   1.0,  0.0,    0.0, 1.0,
-    1.0,   1.0,  0.0,    1.0, 1.0,
-    1.0,  -1.0,  0.0,    1.0, 0.0,
-    ];
-    let vertext_buffer = gfx
-    .create_vertex_buffer()
-    .with_info( &vertex_info )
-    .with_data( &vertices )
-    .build().unwrap();
-    
-    let indices = [0, 1, 2, 2, 3, 0];
-    let index_buffer = gfx
-    .create_index_buffer()
-    .with_data( &indices )
-    .build().unwrap();
-    
-    let transformations = Mat4::from_scale_rotation_translation( scale.into(), Quat::IDENTITY, translation.into() );
-    let transformations_buffer = gfx.create_uniform_buffer( 1, "MeshTransformations" )
-    .build().unwrap();
-
-    Self 
+  r#"
+  #version 450
+  precision mediump float;
+  
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 1 ) in vec4 v_color;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  
+  // Ordered dither thresholds of a 4x4 Bayer matrix, in 16ths.
+  const float BAYER[ 16 ] = float[]( 0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0 );
+  
+  void main()
+  {
+    vec4 texel = texture( u_texture, v_uv );
+    color = vec4( texel.rgb / max( texel.a, 0.00001 ), texel.a ) * v_color;
+    ivec2 cell = ivec2( mod( gl_FragCoord.xy, 4.0 ) );
+    if( color.a <= ( BAYER[ cell.y * 4 + cell.x ] + 0.5 ) / 16.0 )
     {
-      texture,
-      vertext_buffer,
-      index_buffer,
-      transformations_buffer,
-      transformations,
+      discard;
     }
+    color.a = 1.0;
   }
+  "#
+};
 
-  fn draw_texture( &self, pipeline: &Pipeline, gfx : &mut Graphics) 
-  {
-    let mut renderer = gfx.create_renderer();
+/// Alpha cutoff of alpha-tested meshes that do not set one.
+pub const DEFAULT_ALPHA_CUTOFF : f32 = 0.5;
 
-    renderer.begin( None );
+/// Scene file of the scene `main` runs.
+const SCENE_FILE : &str = include_str!( "../scenes/main.json" );
+
+/// Manifest of the assets of the scene `main` runs.
+const MANIFEST_FILE : &str = include_str!( "../scenes/assets.json" );
+
+/// Pipeline drawing meshes with the given fragment shader, blending and depth are left to the caller.
+pub( crate ) fn mesh_pipeline< 'a >( gfx : &'a mut Graphics, fragment : &'a ShaderSource< 'a > ) -> PipelineBuilder< 'a, 'a >
+{
+  let vertex_info = VertexInfo::new()
+  .attr( 0, VertexFormat::Float32x3 ) // positions
+  .attr( 1, VertexFormat::Float32x2 ); // uvs
+
+  gfx
+  .create_pipeline()
+  .from( &VERT, fragment )
+  .with_vertex_info( &vertex_info )
+  .with_texture_location( 0, "u_texture" )
+}
+
+/// Uniform blocks of the mesh shaders and their buffer slots.
+const UNIFORM_BLOCKS : [ ( u32, &str ); 4 ] = [ ( 0, "Camera" ), ( 1, "MeshTransformations" ), ( 2, "MeshMaterial" ), ( 3, "SpriteMaterial" ) ];
+
+/// Bind the uniform blocks of the mesh shaders to their slots in every pipeline.
+///
+/// Temporary workaround for notan 0.5 : its glow backend binds the block of a uniform buffer to its
+/// slot only in the program of the first pipeline the buffer is used with, then sets `block_binded`
+/// on the buffer and never binds it again, so in other programs the block stays on slot 0. The real
+/// buffers can not be reused : the camera buffer is shared by every pipeline and a mesh buffer is
+/// drawn by several. Drawing nothing with a new buffer of every block once in each pipeline binds
+/// them all. Remove it once notan binds blocks per program.
+pub( crate ) fn bind_uniform_blocks( gfx : &mut Graphics, pipelines : &[ &Pipeline ] ) -> Result< (), Error >
+{
+  let mut renderer = gfx.create_renderer();
+  // Kept alive until the renderer is submitted.
+  let mut buffers = vec![];
+  renderer.begin( None );
+  for pipeline in pipelines
+  {
     renderer.set_pipeline( pipeline );
-    
-    if let Some( texture ) = &self.texture.lock()
+    for ( slot, name ) in UNIFORM_BLOCKS
     {
-      renderer.bind_texture( 0, texture );
+      let buffer = gfx.create_uniform_buffer( slot, name )
+      .with_data( &[ 0.0; 16 ] )
+      .build().map_err( Error::buffer )?;
+      renderer.bind_buffer( &buffer );
+      buffers.push( buffer );
     }
-    renderer.bind_buffers( &[ &self.vertext_buffer, &self.index_buffer, &self.transformations_buffer ] );
-    renderer.draw( 0, 6 );
-    renderer.end();
-    gfx.render( &renderer );
   }
+  renderer.end();
+  gfx.render( &renderer );
+  Ok( () )
 }
 
-#[ derive( AppState, Debug ) ]
-pub struct Scene
+/// Camera and viewport the meshes of a frame are drawn with.
+#[ derive( Debug, Clone, Copy ) ]
+pub( crate ) struct View< 'a >
 {
-  pipeline : Pipeline,
-  meshes : Vec< Mesh >,
+  pub camera : &'a Buffer,
+  pub viewport : Viewport,
 }
 
-impl Scene
+impl View< '_ >
+{
+  /// Set the viewport and bind the camera on a renderer with a mesh pipeline set.
+  pub fn bind( &self, renderer : &mut Renderer )
+  {
+    let Viewport { x, y, width, height } = self.viewport;
+    renderer.set_viewport( x, y, width, height );
+    renderer.bind_buffer( self.camera );
+  }
+}
+
+/// Depth-only pipelines laying down the meshes that hide translucent ones in offscreen targets.
+#[ derive( Debug ) ]
+pub( crate ) struct Occluders
+{
+  opaque_pipeline : Pipeline,
+  alpha_tested_pipeline : Pipeline,
+}
+
+impl Occluders
 {
-  fn new( assets : &mut Assets, gfx : &mut Graphics ) -> Scene
+  pub fn new( gfx : &mut Graphics, coverage : Coverage ) -> Result< Self, Error >
   {
-    let depth_test = DepthStencil
+    let depth_write = DepthStencil
     {
       write : true,
       compare : CompareMode::Less,
     };
-    
-    let vertex_info = VertexInfo::new()
-    .attr( 0, VertexFormat::Float32x3 ) // positions
-    .attr( 1, VertexFormat::Float32x2 ); // uvs
-    
-    let pipeline = gfx
-    .create_pipeline()
-    .from( &VERT, &FRAG )
-    .with_color_blend( BlendMode::NORMAL )
-    .with_vertex_info( &vertex_info )
-    .with_texture_location( 0, "u_texture" )
-    .with_depth_stencil( depth_test )
-    .build()
-    .unwrap();
-
-    let meshes = vec![
-      Mesh::new(gfx, assets, "./assets/icon_ethenium.png", ( 0.1, 0.11, 0.1 ), ( -0.11, -0.01, -0.04 )),
-      Mesh::new(gfx, assets, "./assets/icon_voice.png", ( 0.09, 0.09, 0.1 ), ( -0.026, -0.0025, 0.0012 ))
-    ];
-    
-    Scene
-    {
-      pipeline,
-      meshes,
+
+    let opaque_pipeline = mesh_pipeline( gfx, &FRAG )
+    .with_color_mask( ColorMask::NONE )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    let alpha_tested_pipeline = mesh_pipeline( gfx, coverage.fragment() )
+    .with_color_mask( ColorMask::NONE )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    bind_uniform_blocks( gfx, &[ &opaque_pipeline, &alpha_tested_pipeline ] )?;
+
+    Ok( Self
+    {
+      opaque_pipeline,
+      alpha_tested_pipeline,
+    })
+  }
+
+  /// Record the depth of the opaque and alpha-tested meshes, and bind the view for the draws that follow.
+  pub fn draw( &self, renderer : &mut Renderer, meshes : &[ Mesh ], view : &View< '_ > )
+  {
+    renderer.set_pipeline( &self.opaque_pipeline );
+    view.bind( renderer );
+    for mesh in meshes.iter().filter( | mesh | mesh.class() == RenderClass::Opaque )
+    {
+      mesh.draw( renderer );
+    }
+
+    renderer.set_pipeline( &self.alpha_tested_pipeline );
+    for mesh in meshes.iter().filter( | mesh | mesh.class() == RenderClass::AlphaTested )
+    {
+      mesh.draw( renderer );
     }
   }
-  
-  fn render( gfx : &mut Graphics, scene : &mut Self )
+}
+
+/// How a mesh interacts with the depth buffer and with what is already drawn.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize ) ]
+#[ serde( rename_all = "snake_case" ) ]
+pub enum RenderClass
+{
+  /// Fully covers what is behind it, drawn first and writes depth.
+  Opaque,
+  /// Either fully covers or fully reveals what is behind it, texels below the alpha cutoff of the
+  /// mesh are discarded, `DEFAULT_ALPHA_CUTOFF` if it has none.
+  AlphaTested,
+  /// Blended over what is behind it, drawn last from back to front without writing depth.
+  Translucent,
+}
+
+impl RenderClass
+{
+  /// Index of the pipeline drawing the class, in the pipelines of the batchers.
+  pub fn pipeline_index( self ) -> usize
   {
-    for mesh in &mut scene.meshes
+    match self
     {
-      gfx.set_buffer_data( &mesh.transformations_buffer, &mesh.transformations.to_cols_array() );
-      mesh.draw_texture( &scene.pipeline, gfx );
+      RenderClass::Opaque => 0,
+      RenderClass::AlphaTested => 1,
+      RenderClass::Translucent => 2,
     }
   }
 }
 
-#[ wasm_bindgen::prelude::wasm_bindgen ]
-pub fn main() -> Result< (), String >
+/// Loading of the texture of a mesh.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum LoadState
 {
-  let window_config = WindowConfig::default().transparent();
-  notan::init_with( Scene::new )
-  .add_config( window_config )
-  .draw( Scene::render )
-  .build()
+  /// Drawn with a grey checkerboard.
+  Loading,
+  Loaded,
+  /// Not loaded within the `load_timeout` of the scene, drawn with a magenta checkerboard. It does
+  /// not mean the load failed : notan only logs failed loads, so a missing file and a slow one look
+  /// the same, and a texture that loads later is still drawn. Meshes outside of a scene have no
+  /// timeout and stay `Loading`.
+  Failed,
+}
+
+/// Where the texture of a mesh comes from.
+#[ derive( Debug, Clone ) ]
+pub enum TextureSource
+{
+  /// Image file, the scene creates the texture from it with the sampling of the mesh once it loads.
+  File( Asset< ImageFile > ),
+  /// Texture created by the app, drawn as it is sampled. Its texels must already be premultiplied
+  /// for `AlphaMode::Premultiplied`.
+  Texture( Asset< Texture > ),
+}
+
+impl TextureSource
+{
+  /// Path of the file, or name of the texture.
+  pub fn id( &self ) -> &str
+  {
+    match self
+    {
+      TextureSource::File( file ) => file.id(),
+      TextureSource::Texture( texture ) => texture.id(),
+    }
+  }
+
+  pub fn is_loaded( &self ) -> bool
+  {
+    match self
+    {
+      TextureSource::File( file ) => file.is_loaded(),
+      TextureSource::Texture( texture ) => texture.is_loaded(),
+    }
+  }
+}
+
+#[ derive( Debug ) ]
+pub struct Mesh
+{
+  class : RenderClass,
+  source : TextureSource,
+  /// Texture created from the file of the source with `sampling`, once it loaded.
+  texture : Option< Texture >,
+  /// Drawn while the texture is not loaded.
+  placeholder : Texture,
+  load_state : LoadState,
+  sampling : Sampling,
+  /// Seconds waited for the texture.
+  waiting : f32,
+  geometry : SharedGeometry,
+  pub transformations_buffer : Buffer,
+  transform : Transform,
+  /// World matrix as of the last upload.
+  transformations : Mat4,
+  uv_rect : Rect,
+  flip_x : bool,
+  flip_y : bool,
+  pub material_buffer : Buffer,
+  opacity : f32,
+  tint : Color,
+  alpha_cutoff : Option< f32 >,
+  alpha_mode : AlphaMode,
+  animation : Option< Animation >,
+  /// `transform` changed since the world matrix was computed.
+  transform_dirty : bool,
+  /// The world matrix or the uv rectangle changed since the last upload.
+  transformations_dirty : bool,
+  material_dirty : bool,
+}
+
+impl Mesh
+{
+  /// Textured quad sharing its geometry through `geometry`, until the mesh is dropped. The image
+  /// file at `path` is loaded with `image_loader`, a grey checkerboard is drawn while it loads.
+  pub fn new( gfx: &mut Graphics, assets : &mut Assets, geometry : &mut GeometryCache, path : &str, class : RenderClass, scale : impl Into< Vec3 >, translation : impl Into< Vec3 > ) -> Result< Self, Error >
+  {
+    let file = assets.load_asset( path ).map_err( | message | Error::Asset { path : path.to_string(), message } )?;
+    Self::with_source( gfx, geometry, TextureSource::File( file ), class, scale, translation )
+  }
+
+  /// Same as `new` with the texture `source`, meshes can share it.
+  pub fn with_source( gfx: &mut Graphics, geometry : &mut GeometryCache, source : TextureSource, class : RenderClass, scale : impl Into< Vec3 >, translation : impl Into< Vec3 > ) -> Result< Self, Error >
+  {
+    let placeholder = Placeholders::loading( gfx )?;
+    Self::with_placeholder( gfx, geometry, source, placeholder, class, scale, translation )
+  }
+
+  /// Same as `with_source` drawing `placeholder` while the texture loads, meshes of a scene share it.
+  pub( crate ) fn with_placeholder( gfx: &mut Graphics, geometry : &mut GeometryCache, source : TextureSource, placeholder : Texture, class : RenderClass, scale : impl Into< Vec3 >, translation : impl Into< Vec3 > ) -> Result< Self, Error >
+  {
+    let transform = Transform::new( scale, translation );
+    let transformations_buffer = gfx.create_uniform_buffer( 1, "MeshTransformations" )
+    .build().map_err( Error::buffer )?;
+
+    let material_buffer = gfx.create_uniform_buffer( 2, "MeshMaterial" )
+    .build().map_err( Error::buffer )?;
+
+    let geometry = geometry.acquire( gfx, Shape::Quad )?;
+
+    Ok( Self 
+    {
+      class,
+      source,
+      texture : None,
+      placeholder,
+      load_state : LoadState::Loading,
+      sampling : Sampling::default(),
+      waiting : 0.0,
+      geometry,
+      transformations_buffer,
+      transform,
+      transformations : transform.matrix(),
+      uv_rect : Rect { x : 0.0, y : 0.0, width : 1.0, height : 1.0 },
+      flip_x : false,
+      flip_y : false,
+      material_buffer,
+      opacity : 1.0,
+      tint : Color::WHITE,
+      alpha_cutoff : None,
+      alpha_mode : AlphaMode::Straight,
+      animation : None,
+      transform_dirty : true,
+      transformations_dirty : true,
+      material_dirty : true,
+    })
+  }
+
+  pub fn geometry( &self ) -> &Geometry
+  {
+    &self.geometry
+  }
+
+  pub fn class( &self ) -> RenderClass
+  {
+    self.class
+  }
+
+  pub fn set_class( &mut self, class : RenderClass )
+  {
+    if class != self.class
+    {
+      self.class = class;
+      // The default alpha cutoff depends on the class.
+      self.material_dirty = true;
+    }
+  }
+
+  pub fn transform( &self ) -> &Transform
+  {
+    &self.transform
+  }
+
+  /// Transform relative to the parent of the mesh, or to the world without parent. The world
+  /// matrices of the mesh and its descendants are recomputed and uploaded at the next render.
+  pub fn transform_mut( &mut self ) -> &mut Transform
+  {
+    self.transform_dirty = true;
+    &mut self.transform
+  }
+
+  pub fn set_transform( &mut self, transform : Transform )
+  {
+    if transform != self.transform
+    {
+      self.transform = transform;
+      self.transform_dirty = true;
+    }
+  }
+
+  /// Matrix from the local space of the mesh to the world, as of the last render.
+  pub fn transformations( &self ) -> Mat4
+  {
+    self.transformations
+  }
+
+  pub fn uv_rect( &self ) -> Rect
+  {
+    self.uv_rect
+  }
+
+  /// Part of the texture drawn, the whole texture by default. `x` and `y` are the uv offset of its
+  /// top left corner and `width` and `height` its uv scale, uvs start at the top left of the texture
+  /// with v downward, as in `Shape::Quad`. Sprite sheets and atlases are drawn a cell at a time.
+  pub fn set_uv_rect( &mut self, uv_rect : Rect )
+  {
+    if uv_rect != self.uv_rect
+    {
+      self.uv_rect = uv_rect;
+      self.transformations_dirty = true;
+    }
+  }
+
+  /// Whether the uv rectangle is mirrored horizontally and vertically.
+  pub fn flip( &self ) -> ( bool, bool )
+  {
+    ( self.flip_x, self.flip_y )
+  }
+
+  /// Mirror the uv rectangle horizontally with `flip_x`, vertically with `flip_y`.
+  pub fn set_flip( &mut self, flip_x : bool, flip_y : bool )
+  {
+    if ( flip_x, flip_y ) != self.flip()
+    {
+      self.flip_x = flip_x;
+      self.flip_y = flip_y;
+      self.transformations_dirty = true;
+    }
+  }
+
+  pub fn opacity( &self ) -> f32
+  {
+    self.opacity
+  }
+
+  /// Multiplies the alpha of the texture, in `0..=1`.
+  pub fn set_opacity( &mut self, opacity : f32 )
+  {
+    let opacity = opacity.clamp( 0.0, 1.0 );
+    if opacity != self.opacity
+    {
+      self.opacity = opacity;
+      self.material_dirty = true;
+    }
+  }
+
+  pub fn tint( &self ) -> Color
+  {
+    self.tint
+  }
+
+  /// Multiplies the color of the texture, alpha included.
+  pub fn set_tint( &mut self, tint : Color )
+  {
+    if tint != self.tint
+    {
+      self.tint = tint;
+      self.material_dirty = true;
+    }
+  }
+
+  pub fn alpha_cutoff( &self ) -> Option< f32 >
+  {
+    self.alpha_cutoff
+  }
+
+  /// Texels whose alpha, after tint and opacity, is below the cutoff are discarded. `None` keeps
+  /// every texel, except for alpha-tested meshes which use `DEFAULT_ALPHA_CUTOFF`. The cutoff is
+  /// clamped to `0..=1`.
+  pub fn set_alpha_cutoff( &mut self, alpha_cutoff : Option< f32 > )
+  {
+    let alpha_cutoff = alpha_cutoff.map( | cutoff | cutoff.clamp( 0.0, 1.0 ) );
+    if alpha_cutoff != self.alpha_cutoff
+    {
+      self.alpha_cutoff = alpha_cutoff;
+      self.material_dirty = true;
+    }
+  }
+
+  pub fn animation( &self ) -> Option< &Animation >
+  {
+    self.animation.as_ref()
+  }
+
+  /// Animation played by the updates of the scene, from its start.
+  pub fn set_animation( &mut self, animation : Option< Animation > )
+  {
+    self.animation = animation;
+  }
+
+  /// Play `delta` more seconds of the animation and apply its pose.
+  pub( crate ) fn animate( &mut self, delta : f32 ) -> Option< AnimationEvent >
+  {
+    let animation = self.animation.as_mut()?;
+    let event = animation.advance( delta );
+    let pose = animation.pose();
+
+    let mut transform = self.transform;
+    if let Some( translation ) = pose.translation
+    {
+      transform.translation = translation;
+    }
+    if let Some( rotation ) = pose.rotation
+    {
+      transform.rotation = rotation;
+    }
+    if let Some( scale ) = pose.scale
+    {
+      transform.scale = scale;
+    }
+    self.set_transform( transform );
+    if let Some( opacity ) = pose.opacity
+    {
+      self.set_opacity( opacity );
+    }
+    if let Some( tint ) = pose.tint
+    {
+      self.set_tint( tint );
+    }
+    event
+  }
+
+  /// Alpha the fragment shaders output, set by the scene the mesh belongs to.
+  pub( crate ) fn set_alpha_mode( &mut self, alpha_mode : AlphaMode )
+  {
+    if alpha_mode != self.alpha_mode
+    {
+      self.alpha_mode = alpha_mode;
+      self.material_dirty = true;
+    }
+  }
+
+  /// Content of the `MeshMaterial` uniform block, laid out with std140 rules.
+  fn material( &self ) -> [ f32; 8 ]
+  {
+    let Color { r, g, b, a } = self.tint;
+    let premultiplied = if self.alpha_mode == AlphaMode::Premultiplied { 1.0 } else { 0.0 };
+    let cutoff = match ( self.alpha_cutoff, self.class )
+    {
+      ( Some( cutoff ), _ ) => cutoff,
+      ( None, RenderClass::AlphaTested ) => DEFAULT_ALPHA_CUTOFF,
+      // Alpha is never below zero, nothing is discarded.
+      ( None, _ ) => -1.0,
+    };
+    [ r, g, b, a, self.opacity, premultiplied, cutoff, 0.0 ]
+  }
+
+  /// Content of the `MeshTransformations` uniform block.
+  fn transformations_block( &self ) -> [ f32; 20 ]
+  {
+    let mut block = [ 0.0; 20 ];
+    block[ ..16 ].copy_from_slice( &self.transformations.to_cols_array() );
+    block[ 16.. ].copy_from_slice( &self.uv_rect_array() );
+    block
+  }
+
+  /// Attributes of the mesh drawn as an instance.
+  pub( crate ) fn instance( &self ) -> [ f32; INSTANCE_FLOATS ]
+  {
+    let mut instance = [ 0.0; INSTANCE_FLOATS ];
+    instance[ ..16 ].copy_from_slice( &self.transformations.to_cols_array() );
+    instance[ 16..20 ].copy_from_slice( &self.uv_rect_array() );
+    instance[ 20.. ].copy_from_slice( &self.material() );
+    instance
+  }
+
+  /// Quad of the mesh for the sprite batcher.
+  pub( crate ) fn sprite( &self ) -> Sprite
+  {
+    let [ r, g, b, a, opacity, .. ] = self.material();
+    Sprite
+    {
+      transformations : self.transformations,
+      uv_rect : self.drawn_uv_rect(),
+      color : Color::new( r, g, b, a * opacity ),
+    }
+  }
+
+  /// Premultiplied and cutoff of the `SpriteMaterial` uniform block.
+  pub( crate ) fn sprite_material( &self ) -> [ f32; 2 ]
+  {
+    let [ .., premultiplied, cutoff, _ ] = self.material();
+    [ premultiplied, cutoff ]
+  }
+
+  /// Uv rectangle with its flips, a flipped axis starts at the other edge with a negative scale.
+  fn drawn_uv_rect( &self ) -> Rect
+  {
+    let Rect { mut x, mut y, mut width, mut height } = self.uv_rect;
+    if self.flip_x
+    {
+      x += width;
+      width = -width;
+    }
+    if self.flip_y
+    {
+      y += height;
+      height = -height;
+    }
+    Rect { x, y, width, height }
+  }
+
+  fn uv_rect_array( &self ) -> [ f32; 4 ]
+  {
+    let Rect { x, y, width, height } = self.drawn_uv_rect();
+    [ x, y, width, height ]
+  }
+
+  /// Upload the world matrix and the uv rectangle if they changed since the last upload, with the
+  /// world matrix `world` when it was recomputed.
+  pub( crate ) fn update_transformations( &mut self, gfx : &mut Graphics, world : Option< Mat4 > )
+  {
+    self.transform_dirty = false;
+    if let Some( world ) = world.filter( | world | *world != self.transformations )
+    {
+      self.transformations = world;
+      self.transformations_dirty = true;
+    }
+    if self.transformations_dirty
+    {
+      gfx.set_buffer_data( &self.transformations_buffer, &self.transformations_block() );
+      self.transformations_dirty = false;
+    }
+  }
+
+  /// Upload the material if it changed since the last upload.
+  pub( crate ) fn update_material( &mut self, gfx : &mut Graphics )
+  {
+    if self.material_dirty
+    {
+      gfx.set_buffer_data( &self.material_buffer, &self.material() );
+      self.material_dirty = false;
+    }
+  }
+
+  pub fn source( &self ) -> &TextureSource
+  {
+    &self.source
+  }
+
+  /// Whether the texture is loaded, still loading or failed to.
+  pub fn load_state( &self ) -> LoadState
+  {
+    let loaded = match &self.source
+    {
+      TextureSource::File( _ ) => self.texture.is_some(),
+      TextureSource::Texture( texture ) => texture.is_loaded(),
+    };
+    if loaded { LoadState::Loaded } else { self.load_state }
+  }
+
+  /// Texture drawn while the texture of the mesh is not loaded.
+  pub( crate ) fn set_placeholder( &mut self, placeholder : &Texture )
+  {
+    self.placeholder = placeholder.clone();
+  }
+
+  /// Count `delta` more seconds waiting for the texture, after `timeout` seconds it failed and
+  /// `failed` is drawn instead.
+  pub( crate ) fn wait_texture( &mut self, delta : f32, timeout : f32, failed : &Texture )
+  {
+    if self.load_state() != LoadState::Loading
+    {
+      return;
+    }
+    self.waiting += delta;
+    if self.waiting >= timeout
+    {
+      log::warn!( "Texture {} did not load in {timeout} seconds", self.source.id() );
+      self.load_state = LoadState::Failed;
+      self.set_placeholder( failed );
+    }
+  }
+
+  pub fn sampling( &self ) -> Sampling
+  {
+    self.sampling
+  }
+
+  /// Sample the texture with `sampling`. The texture of an image file is created with it, a
+  /// texture created by the app keeps its own sampling. Mipmaps are not supported and are cleared
+  /// with a warning.
+  pub fn set_sampling( &mut self, sampling : Sampling )
+  {
+    if sampling.mipmaps
+    {
+      // `TextureBuilder::generate_mipmap` is not implemented by notan 0.5 and panics.
+      log::warn!( "Mipmaps are not supported, texture {} is sampled without", self.source.id() );
+    }
+    let sampling = sampling.mipmaps( false );
+    if sampling != self.sampling
+    {
+      self.sampling = sampling;
+      self.texture = None;
+    }
+  }
+
+  /// Loaded image file to create the texture from, `None` once the texture is created or while
+  /// the file loads.
+  pub( crate ) fn file_to_create( &self ) -> Option< &Asset< ImageFile > >
+  {
+    match &self.source
+    {
+      TextureSource::File( file ) if self.texture.is_none() && file.is_loaded() => Some( file ),
+      _ => None,
+    }
+  }
+
+  pub( crate ) fn set_texture( &mut self, texture : Texture )
+  {
+    self.texture = Some( texture );
+  }
+
+  /// Texture drawn, the placeholder until the texture is loaded.
+  pub( crate ) fn drawn_texture( &self ) -> Texture
+  {
+    let texture = match &self.source
+    {
+      TextureSource::File( _ ) => self.texture.clone(),
+      TextureSource::Texture( texture ) => texture.lock().map( | texture | texture.clone() ),
+    };
+    texture.unwrap_or_else( || self.placeholder.clone() )
+  }
+
+  /// Id of the texture drawn.
+  pub( crate ) fn texture_id( &self ) -> u64
+  {
+    self.drawn_texture().id()
+  }
+
+  pub( crate ) fn bind_texture( &self, renderer : &mut Renderer )
+  {
+    renderer.bind_texture( 0, &self.drawn_texture() );
+  }
+
+  /// Record the draw of the mesh with the pipeline and view already set on the renderer.
+  pub( crate ) fn draw( &self, renderer : &mut Renderer )
+  {
+    self.bind_texture( renderer );
+    renderer.bind_buffers( &[ &self.geometry.vertex_buffer, &self.geometry.index_buffer, &self.transformations_buffer, &self.material_buffer ] );
+    renderer.draw( 0, self.geometry.count );
+  }
+}
+
+/// How the scene resolves overlapping translucent meshes.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum Transparency
+{
+  /// Sort translucent meshes from back to front, exact unless meshes intersect.
+  Sorted,
+  /// Weighted blended order-independent transparency, approximate but insensitive to order.
+  WeightedBlended,
+  /// Depth peeling, exact for the nearest `layers` translucent layers of every pixel, at least 1.
+  DepthPeeling
+  {
+    layers : usize,
+  },
+}
+
+/// Whether colors are multiplied by their alpha before blending.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum AlphaMode
+{
+  /// Colors are blended as stored in textures, the alpha of the framebuffer is only meaningful for
+  /// an opaque window.
+  Straight,
+  /// Texels are multiplied by alpha when textures are created, before they are filtered, and
+  /// blended with `BlendMode::OVER`. Soft edges keep their color and the framebuffer holds
+  /// premultiplied alpha, which is what a transparent window or canvas composites over the page.
+  Premultiplied,
+}
+
+/// How alpha-tested meshes turn alpha into coverage.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum Coverage
+{
+  /// Texels below the alpha cutoff of the mesh are discarded, edges are hard.
+  AlphaTest,
+  /// Pixels are discarded with a 4x4 ordered dither of alpha, so the share of covered pixels
+  /// follows alpha and soft edges fade while still writing depth.
+  ///
+  /// This is not alpha-to-coverage : notan 0.5 does not expose it, whole pixels are discarded so
+  /// multisampling does not smooth the pattern, and the alpha cutoff of the mesh is not used.
+  Dither,
+}
+
+impl Coverage
+{
+  /// Fragment shader of alpha-tested meshes.
+  fn fragment( self ) -> &'static ShaderSource< 'static >
+  {
+    match self
+    {
+      Coverage::AlphaTest => &FRAG,
+      Coverage::Dither => &FRAG_DITHER,
+    }
+  }
+
+  /// Fragment shader of instanced alpha-tested meshes.
+  pub( crate ) fn instanced_fragment( self, alpha_mode : AlphaMode ) -> &'static ShaderSource< 'static >
+  {
+    match ( self, alpha_mode )
+    {
+      ( Coverage::AlphaTest, _ ) => &FRAG_INSTANCED,
+      ( Coverage::Dither, AlphaMode::Straight ) => &FRAG_DITHER,
+      ( Coverage::Dither, AlphaMode::Premultiplied ) => &FRAG_DITHER_PREMULTIPLIED,
+    }
+  }
+}
+
+/// How the scene groups meshes into draw calls.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+pub enum Batching
+{
+  /// One instanced draw per run of meshes sharing a pipeline and a texture.
+  Instanced,
+  /// Quads transformed on the CPU and drawn in batches of up to `capacity`, for backends without
+  /// instancing.
+  Sprites
+  {
+    capacity : usize,
+  },
+}
+
+/// What the screen is cleared to at the start of every frame, `None` keeps what is there.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct ClearConfig
+{
+  pub color : Option< Color >,
+  pub depth : Option< f32 >,
+  pub stencil : Option< i32 >,
+}
+
+impl Default for ClearConfig
+{
+  /// Fully transparent color, so the page shows through the canvas, and the farthest depth.
+  fn default() -> Self
+  {
+    Self
+    {
+      color : Some( Color::TRANSPARENT ),
+      depth : Some( 1.0 ),
+      stencil : None,
+    }
+  }
+}
+
+impl ClearConfig
+{
+  /// Clear nothing.
+  pub fn none() -> Self
+  {
+    Self
+    {
+      color : None,
+      depth : None,
+      stencil : None,
+    }
+  }
+
+  pub fn color( mut self, color : Option< Color > ) -> Self
+  {
+    self.color = color;
+    self
+  }
+
+  pub fn depth( mut self, depth : Option< f32 > ) -> Self
+  {
+    self.depth = depth;
+    self
+  }
+
+  pub fn stencil( mut self, stencil : Option< i32 > ) -> Self
+  {
+    self.stencil = stencil;
+    self
+  }
+}
+
+impl From< ClearConfig > for ClearOptions
+{
+  fn from( clear : ClearConfig ) -> Self
+  {
+    ClearOptions
+    {
+      color : clear.color,
+      depth : clear.depth,
+      stencil : clear.stencil,
+    }
+  }
+}
+
+/// Options of a scene chosen at construction.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct SceneConfig
+{
+  pub transparency : Transparency,
+  pub alpha_mode : AlphaMode,
+  /// Samples per pixel of the window, 0 disables multisampling. The web backend only tells the
+  /// browser whether to antialias, it picks the count.
+  pub msaa_samples : u16,
+  /// How alpha-tested meshes turn alpha into coverage.
+  pub coverage : Coverage,
+  pub clear : ClearConfig,
+  pub batching : Batching,
+  /// Seconds of every update, `None` runs one update per frame.
+  pub fixed_step : Option< f32 >,
+  /// Seconds after which a texture that is not loaded is `LoadState::Failed` and drawn as failed.
+  /// Notan does not report failed loads to the scene, a texture is failed only by this timeout.
+  pub load_timeout : f32,
+}
+
+impl Default for SceneConfig
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      transparency : Transparency::Sorted,
+      alpha_mode : AlphaMode::Straight,
+      msaa_samples : 0,
+      coverage : Coverage::AlphaTest,
+      clear : ClearConfig::default(),
+      batching : Batching::Instanced,
+      fixed_step : None,
+      load_timeout : 10.0,
+    }
+  }
+}
+
+impl SceneConfig
+{
+  pub fn transparency( mut self, transparency : Transparency ) -> Self
+  {
+    self.transparency = transparency;
+    self
+  }
+
+  pub fn alpha_mode( mut self, alpha_mode : AlphaMode ) -> Self
+  {
+    self.alpha_mode = alpha_mode;
+    self
+  }
+
+  pub fn msaa_samples( mut self, msaa_samples : u16 ) -> Self
+  {
+    self.msaa_samples = msaa_samples;
+    self
+  }
+
+  pub fn coverage( mut self, coverage : Coverage ) -> Self
+  {
+    self.coverage = coverage;
+    self
+  }
+
+  pub fn clear( mut self, clear : ClearConfig ) -> Self
+  {
+    self.clear = clear;
+    self
+  }
+
+  pub fn batching( mut self, batching : Batching ) -> Self
+  {
+    self.batching = batching;
+    self
+  }
+
+  pub fn fixed_step( mut self, fixed_step : Option< f32 > ) -> Self
+  {
+    self.fixed_step = fixed_step;
+    self
+  }
+
+  pub fn load_timeout( mut self, load_timeout : f32 ) -> Self
+  {
+    self.load_timeout = load_timeout;
+    self
+  }
+
+  /// Window options the scene expects.
+  pub fn window_config( &self ) -> WindowConfig
+  {
+    WindowConfig::default()
+    .transparent()
+    .multisampling( self.msaa_samples )
+  }
+}
+
+#[ derive( Debug ) ]
+enum TranslucentPass
+{
+  Sorted,
+  WeightedBlended( Box< WeightedBlended > ),
+  DepthPeeling( Box< DepthPeeling > ),
+}
+
+#[ derive( Debug ) ]
+enum Batcher
+{
+  Instanced( Box< Instanced > ),
+  Sprites( Box< Sprites > ),
+}
+
+#[ derive( AppState, Debug ) ]
+pub struct Scene
+{
+  batcher : Batcher,
+  translucent_pass : TranslucentPass,
+  alpha_mode : AlphaMode,
+  coverage : Coverage,
+  camera : Camera,
+  camera_buffer : Buffer,
+  fit_mode : FitMode,
+  aspect : f32,
+  resized : bool,
+  clear : ClearConfig,
+  stats : FrameStats,
+  manifest : Manifest,
+  preload : Preload,
+  on_ready : Option< fn( &mut Scene ) >,
+  /// Texture sources of the meshes by path, or by name for atlases.
+  textures : HashMap< String, TextureSource >,
+  /// Textures created from the image files by path and sampling, shared by the meshes sampling a
+  /// file the same way, and dropped once none does. `None` if the texture could not be created.
+  created : HashMap< ( String, Sampling ), Option< Texture > >,
+  /// Regions of the atlases by name, with the name of their atlas.
+  regions : HashMap< String, ( String, Rect ) >,
+  placeholders : Placeholders,
+  load_timeout : f32,
+  clock : Clock,
+  on_update : Option< fn( &mut Scene, f32 ) >,
+  animation_events : Vec< ( usize, AnimationEvent ) >,
+  geometry : GeometryCache,
+  graph : Graph,
+  meshes : Vec< Mesh >,
+}
+
+impl Scene
+{
+  /// Configuration of the scene `main` runs.
+  pub fn config() -> SceneConfig
+  {
+    SceneConfig::default()
+    .alpha_mode( AlphaMode::Premultiplied )
+    .msaa_samples( 4 )
+    .coverage( Coverage::Dither )
+  }
+
+  /// Scene of `main`, with the meshes of its scene file.
+  pub fn new( assets : &mut Assets, gfx : &mut Graphics ) -> Result< Scene, Error >
+  {
+    Self::with_config( assets, gfx, Self::config() )
+  }
+
+  pub fn with_config( assets : &mut Assets, gfx : &mut Graphics, config : SceneConfig ) -> Result< Scene, Error >
+  {
+    Self::with_file( assets, gfx, config, &Manifest::from_json( MANIFEST_FILE )?, &SceneFile::from_json( SCENE_FILE )? )
+  }
+
+  /// Scene preloading the assets of `manifest`, with the meshes of `file`.
+  pub fn with_file( assets : &mut Assets, gfx : &mut Graphics, config : SceneConfig, manifest : &Manifest, file : &SceneFile ) -> Result< Scene, Error >
+  {
+    let preload = Preload::new( assets, manifest )?;
+    let textures = preload
+    .assets()
+    .filter_map( | ( entry, loading ) | match loading
+    {
+      Loading::Texture( file ) => Some( ( entry.path.clone(), TextureSource::File( file.clone() ) ) ),
+      Loading::Bytes( _ ) => None,
+    })
+    .collect();
+
+    let coverage = config.coverage;
+    match coverage
+    {
+      Coverage::Dither => log::info!( "Alpha-tested meshes are dithered by alpha" ),
+      Coverage::AlphaTest => log::info!( "Alpha-tested meshes use alpha test" ),
+    }
+
+    let translucent_pass = match config.transparency
+    {
+      Transparency::Sorted => TranslucentPass::Sorted,
+      Transparency::WeightedBlended => TranslucentPass::WeightedBlended( Box::new( WeightedBlended::new( gfx, coverage )? ) ),
+      Transparency::DepthPeeling { layers } => TranslucentPass::DepthPeeling( Box::new( DepthPeeling::new( gfx, layers, coverage )? ) ),
+    };
+
+    let batcher = match config.batching
+    {
+      Batching::Instanced => Batcher::Instanced( Box::new( Instanced::new( gfx, coverage, config.alpha_mode )? ) ),
+      Batching::Sprites { capacity } => Batcher::Sprites( Box::new( Sprites::new( gfx, coverage, config.alpha_mode, capacity )? ) ),
+    };
+
+    let camera_buffer = gfx.create_uniform_buffer( 0, "Camera" )
+    .build().map_err( Error::buffer )?;
+    let ( width, height ) = gfx.size();
+
+    let mut scene = Scene
+    {
+      batcher,
+      translucent_pass,
+      alpha_mode : config.alpha_mode,
+      coverage,
+      camera : Camera::default(),
+      camera_buffer,
+      fit_mode : FitMode::Letterbox,
+      aspect : width as f32 / height.max( 1 ) as f32,
+      resized : false,
+      clear : config.clear,
+      stats : FrameStats::default(),
+      manifest : manifest.clone(),
+      preload,
+      on_ready : None,
+      textures,
+      created : HashMap::new(),
+      regions : HashMap::new(),
+      placeholders : Placeholders::new( gfx )?,
+      load_timeout : config.load_timeout,
+      clock : Clock::new( config.fixed_step ),
+      on_update : None,
+      animation_events : vec![],
+      geometry : GeometryCache::default(),
+      graph : Graph::default(),
+      meshes : vec![],
+    };
+    scene.add_file( gfx, assets, file )?;
+    Ok( scene )
+  }
+
+  /// Add the meshes of `file`, their parents are meshes of the file.
+  pub fn add_file( &mut self, gfx : &mut Graphics, assets : &mut Assets, file : &SceneFile ) -> Result< (), Error >
+  {
+    file.validate()?;
+    let order = file.order();
+    let first = self.meshes.len();
+    // Index in the scene of the meshes of the file.
+    let mut indices = vec![ 0; order.len() ];
+    for ( position, &index ) in order.iter().enumerate()
+    {
+      indices[ index ] = first + position;
+    }
+
+    for &index in &order
+    {
+      let entry = &file.meshes[ index ];
+      let [ x, y, z, w ] = entry.rotation;
+      let mesh = self.add_mesh( gfx, assets, &entry.texture, entry.class, entry.scale, entry.translation )?;
+      mesh.transform_mut().rotation = Quat::from_xyzw( x, y, z, w ).normalize();
+      mesh.set_opacity( entry.opacity );
+      mesh.set_alpha_cutoff( entry.alpha_cutoff );
+      mesh.set_sampling( entry.sampling );
+      if let Some( [ x, y, width, height ] ) = entry.uv_rect
+      {
+        mesh.set_uv_rect( Rect { x, y, width, height } );
+      }
+      mesh.set_flip( entry.flip_x, entry.flip_y );
+    }
+    for ( index, entry ) in file.meshes.iter().enumerate()
+    {
+      // The transform in the file is relative to the parent, it is kept.
+      self.graph.set_parent( indices[ index ], entry.parent.map( | parent | indices[ parent ] ) );
+    }
+    Ok( () )
+  }
+
+  /// Scene file of the meshes, to save the scene.
+  pub fn to_file( &self ) -> SceneFile
+  {
+    let meshes = self.meshes
+    .iter()
+    .enumerate()
+    .map( | ( index, mesh ) |
+    {
+      let transform = mesh.transform();
+      let ( texture, uv_rect ) = self.reference( mesh );
+      let Rect { x, y, width, height } = mesh.uv_rect();
+      let ( flip_x, flip_y ) = mesh.flip();
+      MeshEntry
+      {
+        texture : texture.to_string(),
+        class : mesh.class(),
+        translation : transform.translation.to_array(),
+        rotation : transform.rotation.to_array(),
+        scale : transform.scale.to_array(),
+        opacity : mesh.opacity(),
+        alpha_cutoff : mesh.alpha_cutoff(),
+        sampling : mesh.sampling(),
+        uv_rect : ( mesh.uv_rect() != uv_rect ).then_some( [ x, y, width, height ] ),
+        flip_x,
+        flip_y,
+        parent : self.graph.parent( index ),
+        order : 0,
+      }
+    })
+    .collect();
+    SceneFile { meshes }
+  }
+  
+  pub fn alpha_mode( &self ) -> AlphaMode
+  {
+    self.alpha_mode
+  }
+
+  /// How alpha-tested meshes are drawn.
+  pub fn coverage( &self ) -> Coverage
+  {
+    self.coverage
+  }
+
+  pub fn meshes( &self ) -> &[ Mesh ]
+  {
+    &self.meshes
+  }
+
+  pub fn meshes_mut( &mut self ) -> &mut [ Mesh ]
+  {
+    &mut self.meshes
+  }
+
+  /// Add a textured quad sharing the geometry of the other meshes. `texture` is the name of a
+  /// region of an atlas of the scene, of a texture of the manifest or a path, meshes with the same
+  /// texture share it.
+  pub fn add_mesh( &mut self, gfx : &mut Graphics, assets : &mut Assets, texture : &str, class : RenderClass, scale : impl Into< Vec3 >, translation : impl Into< Vec3 > ) -> Result< &mut Mesh, Error >
+  {
+    let region = self.regions.get( texture ).cloned();
+    let texture = match &region
+    {
+      Some( ( atlas, _ ) ) => self.texture( assets, atlas )?,
+      None => self.texture( assets, texture )?,
+    };
+    let mut mesh = Mesh::with_placeholder( gfx, &mut self.geometry, texture, self.placeholders.loading.clone(), class, scale, translation )?;
+    if let Some( ( _, uv_rect ) ) = region
+    {
+      mesh.set_uv_rect( uv_rect );
+    }
+    mesh.set_alpha_mode( self.alpha_mode );
+    self.graph.push();
+    self.meshes.push( mesh );
+    Ok( self.meshes.last_mut().unwrap() )
+  }
+
+  /// Texture named `reference` in the manifest or at the path `reference`, loaded on first use.
+  fn texture( &mut self, assets : &mut Assets, reference : &str ) -> Result< TextureSource, Error >
+  {
+    let path = self.manifest.path( reference );
+    if let Some( texture ) = self.textures.get( path )
+    {
+      return Ok( texture.clone() );
+    }
+    let file = assets.load_asset( path ).map_err( | message | Error::Asset { path : path.to_string(), message } )?;
+    let texture = TextureSource::File( file );
+    self.textures.insert( path.to_string(), texture.clone() );
+    Ok( texture )
+  }
+
+  /// Add the texture of `atlas` as `name`, meshes can then be added with the name of one of its
+  /// regions and draw that region. Add atlases before the scene files that use their regions.
+  pub fn add_atlas( &mut self, gfx : &mut Graphics, name : &str, atlas : &Atlas ) -> Result< (), Error >
+  {
+    let texture = atlas.texture( gfx, self.alpha_mode )?;
+    self.textures.insert( name.to_string(), TextureSource::Texture( Asset::from_data( name, texture ) ) );
+    for ( region, uv_rect ) in atlas.regions()
+    {
+      self.regions.insert( region.clone(), ( name.to_string(), *uv_rect ) );
+    }
+    Ok( () )
+  }
+
+  /// Name `mesh` refers to its texture by : its region of an atlas, its name in the manifest, or its
+  /// path, with the uv rectangle the name implies.
+  fn reference< 'a >( &'a self, mesh : &'a Mesh ) -> ( &'a str, Rect )
+  {
+    let path = mesh.source().id();
+    self.regions
+    .iter()
+    .find( | ( _, ( atlas, uv_rect ) ) | atlas == path && *uv_rect == mesh.uv_rect() )
+    .map_or_else
+    (
+      || ( self.manifest.name( path ), Rect { x : 0.0, y : 0.0, width : 1.0, height : 1.0 } ),
+      | ( region, ( _, uv_rect ) ) | ( region.as_str(), *uv_rect ),
+    )
+  }
+
+  /// Give the meshes whose image file loaded its texture, sampled as they ask. Meshes whose
+  /// texture can not be created keep their placeholder.
+  fn create_textures( &mut self, gfx : &mut Graphics )
+  {
+    let creates = self.meshes
+    .iter()
+    .filter_map( | mesh | Some( ( mesh.file_to_create()?.id().to_string(), mesh.sampling() ) ) )
+    .any( | key | !self.created.contains_key( &key ) );
+    if creates
+    {
+      self.drop_unused_textures();
+    }
+
+    let alpha_mode = self.alpha_mode;
+    for mesh in &mut self.meshes
+    {
+      let Some( file ) = mesh.file_to_create() else
+      {
+        continue;
+      };
+      let key = ( file.id().to_string(), mesh.sampling() );
+      let texture = match self.created.get( &key )
+      {
+        Some( texture ) => texture.clone(),
+        None =>
+        {
+          let texture = file
+          .lock()
+          .map( | file | mesh.sampling().texture( gfx, &key.0, &file, alpha_mode ) )
+          .transpose()
+          .unwrap_or_else( | error |
+          {
+            log::error!( "{error}" );
+            None
+          });
+          self.created.entry( key ).or_insert( texture ).clone()
+        }
+      };
+      if let Some( texture ) = texture
+      {
+        mesh.set_texture( texture );
+      }
+    }
+  }
+
+  /// Drop the created textures of files no mesh samples that way anymore, since it changed its
+  /// sampling or was removed. Meshes still drawing one keep it until they get their new texture.
+  fn drop_unused_textures( &mut self )
+  {
+    let meshes = &self.meshes;
+    self.created.retain( | ( path, sampling ), _ | meshes.iter().any( | mesh | mesh.source().id() == path && mesh.sampling() == *sampling ) );
+  }
+
+  /// Assets the scene preloads.
+  pub fn manifest( &self ) -> &Manifest
+  {
+    &self.manifest
+  }
+
+  /// Content of the asset `name` of the manifest loaded as bytes, `None` if the manifest has no
+  /// such asset. Lock it once it is loaded, when the scene is ready if it loaded in time.
+  pub fn bytes( &self, name : &str ) -> Option< Asset< Vec< u8 > > >
+  {
+    self.preload.bytes( name ).cloned()
+  }
+
+  /// Fraction of the assets of the manifest loaded.
+  pub fn load_progress( &self ) -> f32
+  {
+    self.preload.progress()
+  }
+
+  /// Whether the assets of the manifest loaded, or failed to within the load timeout. Meshes are
+  /// drawn once the scene is ready.
+  pub fn is_ready( &self ) -> bool
+  {
+    self.preload.is_ready()
+  }
+
+  /// Function called once, on the update the scene becomes ready.
+  pub fn set_on_ready( &mut self, on_ready : Option< fn( &mut Scene ) > )
+  {
+    self.on_ready = on_ready;
+  }
+
+  /// Remove and drop a mesh, the geometry and texture are freed with their last mesh. Its children
+  /// are detached and keep their place in the world.
+  pub fn remove_mesh( &mut self, index : usize )
+  {
+    let children : Vec< usize > = self.graph.children( index ).collect();
+    for child in children
+    {
+      self.detach( child );
+    }
+    self.graph.remove( index );
+    self.meshes.remove( index );
+    self.drop_unused_textures();
+  }
+
+  /// Parent-child relationships of the meshes, indexed like `meshes`.
+  pub fn graph( &self ) -> &Graph
+  {
+    &self.graph
+  }
+
+  /// Matrices from the local space of the meshes to the world, from their transforms and parents.
+  pub fn world_transformations( &self ) -> Vec< Mat4 >
+  {
+    let locals : Vec< Mat4 > = self.meshes.iter().map( | mesh | mesh.transform().matrix() ).collect();
+    self.graph.propagate( &locals )
+  }
+
+  /// World matrices of the meshes whose transform, or the transform of an ancestor, changed since
+  /// the last render, `None` for the others.
+  fn changed_world_transformations( &self ) -> Vec< Option< Mat4 > >
+  {
+    let dirty : Vec< bool > = self.meshes.iter().map( | mesh | mesh.transform_dirty ).collect();
+    let mut worlds : Vec< Mat4 > = self.meshes.iter().map( Mesh::transformations ).collect();
+    let changed = self.graph.propagate_dirty( &dirty, | index | self.meshes[ index ].transform().matrix(), &mut worlds );
+    worlds.into_iter().zip( changed ).map( | ( world, changed ) | changed.then_some( world ) ).collect()
+  }
+
+  /// Attach the mesh `index` to the mesh `parent`, or detach it with `None`, keeping its place in
+  /// the world. The mesh keeps its transform instead if it or the parent is scaled to zero.
+  /// Returns `false` and changes nothing if `parent` is the mesh or one of its descendants.
+  pub fn reparent( &mut self, index : usize, parent : Option< usize > ) -> bool
+  {
+    if parent.is_some_and( | parent | self.graph.is_ancestor( index, parent ) )
+    {
+      return false;
+    }
+
+    let worlds = self.world_transformations();
+    let mesh = &mut self.meshes[ index ];
+    // The world of the mesh is recomputed with its new parent, even if the local matrix is kept.
+    let transform = mesh.transform_mut();
+    if let Some( local ) = keep_world( worlds[ index ], parent.map( | parent | worlds[ parent ] ) )
+    {
+      *transform = Transform::from_matrix( local );
+    }
+    self.graph.set_parent( index, parent )
+  }
+
+  /// Make the mesh `index` a root, keeping its place in the world.
+  pub fn detach( &mut self, index : usize )
+  {
+    self.reparent( index, None );
+  }
+
+  pub fn geometry( &self ) -> &GeometryCache
+  {
+    &self.geometry
+  }
+
+  pub fn camera( &self ) -> &Camera
+  {
+    &self.camera
+  }
+
+  pub fn camera_mut( &mut self ) -> &mut Camera
+  {
+    &mut self.camera
+  }
+
+  pub fn fit_mode( &self ) -> FitMode
+  {
+    self.fit_mode
+  }
+
+  pub fn set_fit_mode( &mut self, fit_mode : FitMode )
+  {
+    self.fit_mode = fit_mode;
+  }
+
+  /// Width over height of the scene, the window size at construction unless set.
+  pub fn aspect( &self ) -> f32
+  {
+    self.aspect
+  }
+
+  pub fn set_aspect( &mut self, aspect : f32 )
+  {
+    self.aspect = aspect;
+  }
+
+  pub fn clear( &self ) -> ClearConfig
+  {
+    self.clear
+  }
+
+  pub fn set_clear( &mut self, clear : ClearConfig )
+  {
+    self.clear = clear;
+  }
+
+  /// What the scene renderer recorded last frame. Offscreen translucent passes record their own
+  /// renderers and are not counted.
+  pub fn frame_stats( &self ) -> FrameStats
+  {
+    self.stats
+  }
+
+  pub fn clock( &self ) -> &Clock
+  {
+    &self.clock
+  }
+
+  /// Animation events of the meshes during the last frame of updates, with the index of the mesh.
+  pub fn animation_events( &self ) -> &[ ( usize, AnimationEvent ) ]
+  {
+    &self.animation_events
+  }
+
+  /// Function called on every update with the seconds it covers, after the animations of the
+  /// meshes advanced.
+  pub fn set_on_update( &mut self, on_update : Option< fn( &mut Scene, f32 ) > )
+  {
+    self.on_update = on_update;
+  }
+
+  fn update( app : &mut App, scene : &mut Self )
+  {
+    let delta = app.timer.delta_f32();
+    if scene.preload.update( delta, scene.load_timeout )
+    {
+      if let Some( on_ready ) = scene.on_ready
+      {
+        on_ready( scene );
+      }
+    }
+    for mesh in &mut scene.meshes
+    {
+      mesh.wait_texture( delta, scene.load_timeout, &scene.placeholders.failed );
+    }
+
+    let steps = scene.clock.tick( delta );
+    scene.animation_events.clear();
+    for _ in 0..steps.count
+    {
+      for ( index, mesh ) in scene.meshes.iter_mut().enumerate()
+      {
+        if let Some( event ) = mesh.animate( steps.delta )
+        {
+          scene.animation_events.push( ( index, event ) );
+        }
+      }
+      if let Some( on_update ) = scene.on_update
+      {
+        on_update( scene, steps.delta );
+      }
+    }
+  }
+
+  fn event( scene : &mut Self, event : Event )
+  {
+    if let Event::WindowResize { .. } = event
+    {
+      // Graphics is not available to event handlers, targets are recreated on the next frame.
+      scene.resized = true;
+    }
+  }
+  
+  fn render( gfx : &mut Graphics, scene : &mut Self )
+  {
+    if std::mem::take( &mut scene.resized )
+    {
+      let resized = match &mut scene.translucent_pass
+      {
+        TranslucentPass::Sorted => Ok( () ),
+        TranslucentPass::WeightedBlended( pass ) => pass.resize( gfx ),
+        TranslucentPass::DepthPeeling( pass ) => pass.resize( gfx ),
+      };
+      if let Err( error ) = resized
+      {
+        log::error!( "{error}" );
+      }
+    }
+
+    if !scene.preload.is_ready()
+    {
+      // Only clear while the assets preload, the page shows the progress.
+      let mut renderer = gfx.create_renderer();
+      renderer.begin( Some( &scene.clear.into() ) );
+      renderer.end();
+      gfx.render( &renderer );
+      scene.stats = FrameStats::default();
+      return;
+    }
+
+    scene.create_textures( gfx );
+
+    // The viewport has the aspect of the scene, or stretches it.
+    let ( width, height ) = gfx.size();
+    gfx.set_buffer_data( &scene.camera_buffer, &scene.camera.view_projection( scene.aspect ).to_cols_array() );
+    let view = View
+    {
+      camera : &scene.camera_buffer,
+      viewport : scene.fit_mode.viewport( scene.aspect, width as f32, height as f32 ),
+    };
+
+    let worlds = scene.changed_world_transformations();
+    for ( mesh, world ) in scene.meshes.iter_mut().zip( worlds )
+    {
+      mesh.update_transformations( gfx, world );
+      mesh.update_material( gfx );
+    }
+
+    let draw = | ( index, mesh ) : ( usize, &Mesh ) | Draw
+    {
+      pipeline : mesh.class().pipeline_index(),
+      texture : Some( mesh.texture_id() ),
+      shape : mesh.geometry().shape,
+      mesh : index,
+    };
+    let mut list = DrawList::default();
+    list.extend_unordered( scene.meshes.iter().enumerate().filter( | ( _, mesh ) | mesh.class() != RenderClass::Translucent ).map( draw ) );
+
+    if let TranslucentPass::Sorted = scene.translucent_pass
+    {
+      let translucent : Vec< ( usize, &Mesh ) > = scene.meshes
+      .iter()
+      .enumerate()
+      .filter( | ( _, mesh ) | mesh.class() == RenderClass::Translucent )
+      .collect();
+      let order = back_to_front( &scene.camera.view(), translucent.iter().map( | ( _, mesh ) | &mesh.transformations ) );
+      list.extend_ordered( order.into_iter().map( | index | draw( translucent[ index ] ) ) );
+    }
+
+    // The scene renderer is the first to draw on the screen every frame.
+    let clear = scene.clear.into();
+    scene.stats = match &mut scene.batcher
+    {
+      Batcher::Instanced( instanced ) => instanced.render( gfx, &scene.meshes, &list, &view, &clear ),
+      Batcher::Sprites( sprites ) => sprites.render( gfx, &scene.meshes, &list, &view, &clear ),
+    };
+
+    match &scene.translucent_pass
+    {
+      TranslucentPass::Sorted => (),
+      TranslucentPass::WeightedBlended( pass ) => pass.render( gfx, &scene.meshes, &view ),
+      TranslucentPass::DepthPeeling( pass ) => pass.render( gfx, &scene.meshes, &view ),
+    }
+  }
+}
+
+thread_local!
+{
+  /// Load progress and readiness of the scene of `main`, for the page.
+  static PRELOAD : Cell< ( f32, bool ) > = const { Cell::new( ( 0.0, false ) ) };
+}
+
+/// Fraction of the assets of the scene of `main` loaded, for a loading bar.
+#[ wasm_bindgen::prelude::wasm_bindgen ]
+pub fn load_progress() -> f32
+{
+  PRELOAD.with( | preload | preload.get().0 )
+}
+
+/// Whether the scene of `main` is ready and draws its meshes.
+#[ wasm_bindgen::prelude::wasm_bindgen ]
+pub fn is_ready() -> bool
+{
+  PRELOAD.with( | preload | preload.get().1 )
+}
+
+/// Dispatch the event `name` on the window of the page, with the load progress `progress` as detail.
+#[ cfg( target_arch = "wasm32" ) ]
+fn dispatch( name : &str, progress : f32 )
+{
+  let init = web_sys::CustomEventInit::new();
+  init.set_detail( &wasm_bindgen::JsValue::from_f64( f64::from( progress ) ) );
+  let dispatched = web_sys::window()
+  .ok_or( wasm_bindgen::JsValue::NULL )
+  .and_then( | window |
+  {
+    let event = web_sys::CustomEvent::new_with_event_init_dict( name, &init )?;
+    window.dispatch_event( &event )
+  });
+  if let Err( error ) = dispatched
+  {
+    log::error!( "Can not dispatch {name} : {error:?}" );
+  }
+}
+
+/// Pages only exist on the web.
+#[ cfg( not( target_arch = "wasm32" ) ) ]
+fn dispatch( _name : &str, _progress : f32 )
+{
+}
+
+/// State of the app, no scene if it could not be created.
+#[ derive( AppState ) ]
+struct Root
+{
+  scene : Option< Scene >,
+}
+
+impl Root
+{
+  /// Create the scene of `manifest` and `file`. On failure the error is printed and the process
+  /// exits, except on the web where it is kept in `failure` for `main` to return.
+  fn new( app : &mut App, assets : &mut Assets, gfx : &mut Graphics, manifest : &Manifest, file : &SceneFile, failure : &RefCell< Option< Error > > ) -> Self
+  {
+    let scene = Scene::with_file( assets, gfx, Scene::config(), manifest, file )
+    .map_err( | error |
+    {
+      // `EventLoop::run` never returns on native targets, the error would not reach `main`.
+      if cfg!( not( target_arch = "wasm32" ) )
+      {
+        eprintln!( "{error}" );
+        std::process::exit( 1 );
+      }
+      log::error!( "{error}" );
+      *failure.borrow_mut() = Some( error );
+      app.exit();
+    })
+    .ok();
+    Self { scene }
+  }
+
+  fn update( app : &mut App, root : &mut Self )
+  {
+    if let Some( scene ) = &mut root.scene
+    {
+      let ( progress, ready ) = PRELOAD.with( Cell::get );
+      Scene::update( app, scene );
+      PRELOAD.with( | preload | preload.set( ( scene.load_progress(), scene.is_ready() ) ) );
+
+      if scene.load_progress() != progress
+      {
+        dispatch( "scene-progress", scene.load_progress() );
+      }
+      if scene.is_ready() && !ready
+      {
+        dispatch( "scene-ready", scene.load_progress() );
+      }
+    }
+  }
+
+  fn event( root : &mut Self, event : Event )
+  {
+    if let Some( scene ) = &mut root.scene
+    {
+      Scene::event( scene, event );
+    }
+  }
+
+  fn render( gfx : &mut Graphics, root : &mut Self )
+  {
+    if let Some( scene ) = &mut root.scene
+    {
+      Scene::render( gfx, scene );
+    }
+  }
+}
+
+/// Run the scene, the error is readable if the scene could not be created.
+#[ wasm_bindgen::prelude::wasm_bindgen ]
+pub fn main() -> Result< (), String >
+{
+  // Errors of the files are returned before the event loop starts, it never returns on native targets.
+  let manifest = Manifest::from_json( MANIFEST_FILE ).map_err( | error | error.to_string() )?;
+  let file = SceneFile::from_json( SCENE_FILE ).map_err( | error | error.to_string() )?;
+  let window_config = Scene::config().window_config();
+  // The setup runs before `build` returns on the web.
+  let failure = Rc::new( RefCell::new( None ) );
+  let setup_failure = failure.clone();
+  notan::init_with( move | app : &mut App, assets : &mut Assets, gfx : &mut Graphics | Root::new( app, assets, gfx, &manifest, &file, &setup_failure ) )
+  .add_config( window_config )
+  .add_loader( image_loader() )
+  .update( Root::update )
+  .event( Root::event )
+  .draw( Root::render )
+  .build()?;
+
+  match failure.take()
+  {
+    Some( error ) => Err( error.to_string() ),
+    None => Ok( () ),
+  }
 }
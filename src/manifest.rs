@@ -0,0 +1,262 @@
+//!
+//! Asset manifests : the named assets a scene preloads before its first frame.
+//!
+
+use std::collections::HashSet;
+use notan::prelude::*;
+use notan::log;
+use serde::{ Deserialize, Serialize };
+use crate::error::Error;
+use crate::sampling::ImageFile;
+
+/// How an asset is loaded.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize ) ]
+#[ serde( rename_all = "snake_case" ) ]
+pub enum AssetKind
+{
+  /// PNG or JPEG file the textures of the meshes are created from.
+  Texture,
+  /// Raw content of the file, see `Scene::bytes`.
+  Bytes,
+}
+
+#[ derive( Debug, Clone, PartialEq, Eq, Serialize, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+pub struct AssetEntry
+{
+  /// Name scene files refer to the asset by.
+  pub name : String,
+  /// Path relative to the served directory.
+  pub path : String,
+  pub kind : AssetKind,
+}
+
+/// Assets of a scene.
+#[ derive( Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+pub struct Manifest
+{
+  pub assets : Vec< AssetEntry >,
+}
+
+impl Manifest
+{
+  /// Parse and validate a manifest.
+  pub fn from_json( json : &str ) -> Result< Self, Error >
+  {
+    let manifest : Self = serde_json::from_str( json ).map_err( | error | Error::Parse( error.to_string() ) )?;
+    manifest.validate()?;
+    Ok( manifest )
+  }
+
+  pub fn to_json( &self ) -> String
+  {
+    serde_json::to_string_pretty( self ).unwrap()
+  }
+
+  /// Check that names are unique and that names and paths are not empty.
+  pub fn validate( &self ) -> Result< (), Error >
+  {
+    let invalid = | asset : usize, field : &'static str, message : &str | Error::InvalidAsset { asset, field, message : message.to_string() };
+
+    let mut names = HashSet::new();
+    for ( index, entry ) in self.assets.iter().enumerate()
+    {
+      if entry.name.is_empty()
+      {
+        return Err( invalid( index, "name", "is empty" ) );
+      }
+      if !names.insert( entry.name.as_str() )
+      {
+        return Err( invalid( index, "name", "is the name of a previous asset" ) );
+      }
+      if entry.path.is_empty()
+      {
+        return Err( invalid( index, "path", "is empty" ) );
+      }
+    }
+    Ok( () )
+  }
+
+  pub fn get( &self, name : &str ) -> Option< &AssetEntry >
+  {
+    self.assets.iter().find( | entry | entry.name == name )
+  }
+
+  /// Path of the asset named `reference`, or `reference` itself if no asset has this name.
+  pub fn path< 'a >( &'a self, reference : &'a str ) -> &'a str
+  {
+    self.get( reference ).map_or( reference, | entry | entry.path.as_str() )
+  }
+
+  /// Name of the asset at `path`, or `path` itself if no asset is at this path.
+  pub fn name< 'a >( &'a self, path : &'a str ) -> &'a str
+  {
+    self.assets.iter().find( | entry | entry.path == path ).map_or( path, | entry | entry.name.as_str() )
+  }
+}
+
+/// Fraction of `total` assets that are `loaded`, 1 without assets.
+pub fn progress( loaded : usize, total : usize ) -> f32
+{
+  if total == 0 { 1.0 } else { loaded as f32 / total as f32 }
+}
+
+/// Asset of the manifest being loaded.
+#[ derive( Debug, Clone ) ]
+pub enum Loading
+{
+  Texture( Asset< ImageFile > ),
+  Bytes( Asset< Vec< u8 > > ),
+}
+
+impl Loading
+{
+  pub fn is_loaded( &self ) -> bool
+  {
+    match self
+    {
+      Loading::Texture( asset ) => asset.is_loaded(),
+      Loading::Bytes( asset ) => asset.is_loaded(),
+    }
+  }
+}
+
+/// Loading of the assets of a manifest.
+#[ derive( Debug ) ]
+pub( crate ) struct Preload
+{
+  assets : Vec< ( AssetEntry, Loading ) >,
+  /// Seconds waited for the assets.
+  waiting : f32,
+  ready : bool,
+}
+
+impl Preload
+{
+  /// Start loading every asset of `manifest`.
+  pub fn new( assets : &mut Assets, manifest : &Manifest ) -> Result< Self, Error >
+  {
+    let loading = manifest.assets
+    .iter()
+    .map( | entry |
+    {
+      let error = | message | Error::Asset { path : entry.path.clone(), message };
+      let loading = match entry.kind
+      {
+        AssetKind::Texture => Loading::Texture( assets.load_asset( &entry.path ).map_err( error )? ),
+        AssetKind::Bytes => Loading::Bytes( assets.load_asset( &entry.path ).map_err( error )? ),
+      };
+      Ok( ( entry.clone(), loading ) )
+    })
+    .collect::< Result< _, Error > >()?;
+
+    Ok( Self
+    {
+      assets : loading,
+      waiting : 0.0,
+      // Even without assets, so that the first update reports the preload ready.
+      ready : false,
+    })
+  }
+
+  /// Assets of the manifest with their loading.
+  pub fn assets( &self ) -> impl Iterator< Item = ( &AssetEntry, &Loading ) >
+  {
+    self.assets.iter().map( | ( entry, loading ) | ( entry, loading ) )
+  }
+
+  /// Content of the bytes asset `name`, loading or loaded.
+  pub fn bytes( &self, name : &str ) -> Option< &Asset< Vec< u8 > > >
+  {
+    self.assets.iter().find_map( | ( entry, loading ) | match loading
+    {
+      Loading::Bytes( bytes ) if entry.name == name => Some( bytes ),
+      _ => None,
+    })
+  }
+
+  pub fn loaded( &self ) -> usize
+  {
+    self.assets.iter().filter( | ( _, loading ) | loading.is_loaded() ).count()
+  }
+
+  pub fn progress( &self ) -> f32
+  {
+    progress( self.loaded(), self.assets.len() )
+  }
+
+  /// Whether every asset loaded, or the wait timed out.
+  pub fn is_ready( &self ) -> bool
+  {
+    self.ready
+  }
+
+  /// Count `delta` more seconds of waiting. Returns `true` once, when the preload becomes ready
+  /// because every asset loaded or `timeout` seconds passed.
+  pub fn update( &mut self, delta : f32, timeout : f32 ) -> bool
+  {
+    if self.ready
+    {
+      return false;
+    }
+
+    self.waiting += delta;
+    let loaded = self.loaded() == self.assets.len();
+    if !loaded && self.waiting < timeout
+    {
+      return false;
+    }
+
+    for ( entry, _ ) in self.assets.iter().filter( | ( _, loading ) | !loading.is_loaded() )
+    {
+      log::warn!( "Asset {} at {} did not load in {timeout} seconds", entry.name, entry.path );
+    }
+    self.ready = true;
+    true
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn entry( name : &str, path : &str ) -> AssetEntry
+  {
+    AssetEntry { name : name.to_string(), path : path.to_string(), kind : AssetKind::Texture }
+  }
+
+  fn invalid( manifest : &Manifest ) -> Option< ( usize, &'static str ) >
+  {
+    match manifest.validate()
+    {
+      Err( Error::InvalidAsset { asset, field, .. } ) => Some( ( asset, field ) ),
+      _ => None,
+    }
+  }
+
+  #[ test ]
+  fn names_are_unique_and_not_empty()
+  {
+    let mut manifest = Manifest { assets : vec![ entry( "icon", "./icon.png" ), entry( "voice", "./voice.png" ) ] };
+    assert_eq!( manifest.validate(), Ok( () ) );
+
+    manifest.assets[ 1 ].name = "icon".to_string();
+    assert_eq!( invalid( &manifest ), Some( ( 1, "name" ) ) );
+    manifest.assets[ 1 ].name = String::new();
+    assert_eq!( invalid( &manifest ), Some( ( 1, "name" ) ) );
+    manifest.assets[ 1 ].name = "voice".to_string();
+    manifest.assets[ 0 ].path = String::new();
+    assert_eq!( invalid( &manifest ), Some( ( 0, "path" ) ) );
+  }
+
+  #[ test ]
+  fn progress_is_the_loaded_fraction()
+  {
+    assert_eq!( progress( 0, 4 ), 0.0 );
+    assert_eq!( progress( 1, 4 ), 0.25 );
+    assert_eq!( progress( 4, 4 ), 1.0 );
+    assert_eq!( progress( 0, 0 ), 1.0 );
+  }
+}
@@ -4,5 +4,9 @@
 
 fn main()
 {
-  notan_opacity_problem_lib::main().unwrap();
+  if let Err( error ) = notan_opacity_problem_lib::main()
+  {
+    eprintln!( "{error}" );
+    std::process::exit( 1 );
+  }
 }
@@ -3,5 +3,42 @@
 //!
 
 mod lib;
+pub mod animation;
+pub mod atlas;
+pub mod cache;
+pub mod camera;
+pub mod clock;
+pub mod depth_sort;
+pub mod draw_list;
+pub mod error;
+pub mod fit;
+pub mod geometry;
+pub mod graph;
+pub mod manifest;
+pub mod oit;
+pub mod peel;
+pub mod sampling;
+pub mod scene_file;
+pub mod sprite_batch;
+pub mod transform;
+mod screen_quad;
+mod instancing;
+mod sprites;
+mod placeholder;
+mod weighted_blended;
+mod depth_peeling;
 
-pub use lib::main;
+pub use animation::{ Animation, AnimationEvent, Easing, Keyframe, Repeat, Track };
+pub use atlas::{ Atlas, AtlasBuilder, Image };
+pub use camera::{ Camera, Projection };
+pub use clock::Clock;
+pub use draw_list::FrameStats;
+pub use error::Error;
+pub use fit::{ FitMode, Viewport };
+pub use geometry::{ Geometry, GeometryCache, Shape, SharedGeometry };
+pub use graph::Graph;
+pub use manifest::{ AssetEntry, AssetKind, Manifest };
+pub use sampling::{ image_loader, Filter, ImageFile, Sampling, Wrap };
+pub use scene_file::{ MeshEntry, SceneFile };
+pub use transform::Transform;
+pub use lib::{ is_ready, load_progress, main, AlphaMode, Batching, ClearConfig, Coverage, LoadState, Mesh, RenderClass, Scene, SceneConfig, TextureSource, Transparency, DEFAULT_ALPHA_CUTOFF };
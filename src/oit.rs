@@ -0,0 +1,177 @@
+//!
+//! Weighted blended order-independent transparency, the math behind the weighted blended pass.
+//!
+
+use notan::prelude::Color;
+
+/// Lowest depth weight, keeps far fragments from vanishing in the average.
+pub const MIN_WEIGHT : f32 = 0.01;
+/// Highest depth weight, the accumulation target stores 8 bits per channel and saturates at 1.
+pub const MAX_WEIGHT : f32 = 1.0;
+/// Smallest accumulated weight the average color is divided by.
+pub const MIN_ACCUMULATED : f32 = 0.00001;
+
+/// Translucent fragment covering a pixel.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Fragment
+{
+  /// Straight alpha color.
+  pub color : Color,
+  /// Window depth in `0..=1`, 0 is the nearest.
+  pub depth : f32,
+}
+
+/// Weight of a fragment in the average : nearer and more opaque fragments weigh more.
+///
+/// Same as `weight` in the accumulation shader.
+pub fn weight( depth : f32, alpha : f32 ) -> f32
+{
+  alpha * ( 1.0 - depth ).powi( 3 ).clamp( MIN_WEIGHT, MAX_WEIGHT )
+}
+
+/// Content of the accumulation and revealage targets for one pixel.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Accumulation
+{
+  /// Sum of weighted colors in rgb and sum of weights in alpha.
+  pub accumulation : Color,
+  /// Product of the transparencies of every fragment, how much of the background shows through.
+  pub revealage : f32,
+}
+
+impl Default for Accumulation
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      accumulation : Color::TRANSPARENT,
+      revealage : 1.0,
+    }
+  }
+}
+
+impl Accumulation
+{
+  /// Blend a fragment in, in any order.
+  ///
+  /// Saturates like the 8 bits per channel targets do.
+  pub fn add( &mut self, fragment : &Fragment )
+  {
+    let Color { r, g, b, a } = fragment.color;
+    let weight = weight( fragment.depth, a );
+    let accumulation = self.accumulation;
+
+    self.accumulation = Color::new
+    (
+      ( accumulation.r + r * weight ).min( 1.0 ),
+      ( accumulation.g + g * weight ).min( 1.0 ),
+      ( accumulation.b + b * weight ).min( 1.0 ),
+      ( accumulation.a + weight ).min( 1.0 ),
+    );
+    self.revealage *= 1.0 - a;
+  }
+
+  /// Resolve the pixel over the background, same as the composite shader and its blending.
+  pub fn composite( &self, background : Color ) -> Color
+  {
+    let weights = self.accumulation.a.max( MIN_ACCUMULATED );
+    let coverage = 1.0 - self.revealage;
+    let over = | average : f32, background : f32 | average / weights * coverage + background * self.revealage;
+
+    Color::new
+    (
+      over( self.accumulation.r, background.r ),
+      over( self.accumulation.g, background.g ),
+      over( self.accumulation.b, background.b ),
+      coverage + background.a * self.revealage,
+    )
+  }
+}
+
+/// Color of a pixel covered by the fragments, in any order, using weighted blended transparency.
+pub fn weighted_blended( fragments : &[ Fragment ], background : Color ) -> Color
+{
+  let mut accumulation = Accumulation::default();
+  for fragment in fragments
+  {
+    accumulation.add( fragment );
+  }
+  accumulation.composite( background )
+}
+
+/// Exact color of a pixel covered by the fragments : sorted and blended from back to front.
+///
+/// The reference the weighted blended result approximates.
+pub fn sorted( fragments : &[ Fragment ], background : Color ) -> Color
+{
+  let mut fragments = fragments.to_vec();
+  fragments.sort_by( | a, b | b.depth.total_cmp( &a.depth ) );
+
+  fragments.iter().fold( background, | destination, fragment |
+  {
+    let Color { r, g, b, a } = fragment.color;
+    Color::new
+    (
+      r * a + destination.r * ( 1.0 - a ),
+      g * a + destination.g * ( 1.0 - a ),
+      b * a + destination.b * ( 1.0 - a ),
+      a + destination.a * ( 1.0 - a ),
+    )
+  })
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn close( a : Color, b : Color, epsilon : f32 ) -> bool
+  {
+    ( a.r - b.r ).abs() < epsilon && ( a.g - b.g ).abs() < epsilon && ( a.b - b.b ).abs() < epsilon && ( a.a - b.a ).abs() < epsilon
+  }
+
+  #[ test ]
+  fn weight_favours_near_and_opaque()
+  {
+    assert!( weight( 0.1, 0.5 ) > weight( 0.9, 0.5 ) );
+    assert!( weight( 0.5, 0.8 ) > weight( 0.5, 0.2 ) );
+    assert_eq!( weight( 1.0, 1.0 ), MIN_WEIGHT );
+    assert_eq!( weight( 0.0, 1.0 ), MAX_WEIGHT );
+  }
+
+  #[ test ]
+  fn nothing_shows_the_background()
+  {
+    let background = Color::new( 0.2, 0.4, 0.6, 1.0 );
+    assert!( close( weighted_blended( &[], background ), background, 1e-6 ) );
+    assert!( close( sorted( &[], background ), background, 1e-6 ) );
+  }
+
+  #[ test ]
+  fn one_fragment_matches_sorted()
+  {
+    let fragments = [ Fragment { color : Color::new( 1.0, 0.0, 0.0, 0.5 ), depth : 0.3 } ];
+    assert!( close( weighted_blended( &fragments, Color::BLACK ), sorted( &fragments, Color::BLACK ), 1e-5 ) );
+  }
+
+  #[ test ]
+  fn order_independent()
+  {
+    let a = Fragment { color : Color::new( 1.0, 0.0, 0.0, 0.4 ), depth : 0.2 };
+    let b = Fragment { color : Color::new( 0.0, 0.0, 1.0, 0.6 ), depth : 0.7 };
+    assert!( close( weighted_blended( &[ a, b ], Color::WHITE ), weighted_blended( &[ b, a ], Color::WHITE ), 1e-6 ) );
+    // The background shows through as much as with sorted blending, only the colors of the
+    // fragments are an approximation : green comes from the background alone.
+    let ( approximated, exact ) = ( weighted_blended( &[ a, b ], Color::WHITE ), sorted( &[ a, b ], Color::WHITE ) );
+    assert!( ( approximated.g - exact.g ).abs() < 1e-6 );
+    assert!( ( approximated.a - exact.a ).abs() < 1e-6 );
+  }
+
+  #[ test ]
+  fn opaque_fragment_hides_the_background()
+  {
+    let fragments = [ Fragment { color : Color::new( 0.0, 1.0, 0.0, 1.0 ), depth : 0.5 } ];
+    assert!( close( weighted_blended( &fragments, Color::RED ), Color::GREEN, 1e-5 ) );
+  }
+}
@@ -0,0 +1,166 @@
+//!
+//! Depth peeling reference : peels and composites fragments the way the depth peeling pass does.
+//!
+
+use notan::prelude::Color;
+use notan::math::{ Mat2, Mat4, Vec2, Vec3 };
+use crate::oit::Fragment;
+
+/// Peel up to `layers` layers, nearest first.
+///
+/// Each layer keeps the nearest fragment strictly behind the previous layer. Like the depth test,
+/// fragments at the same depth are resolved to the first one in the slice and the others are lost.
+pub fn peel( fragments : &[ Fragment ], layers : usize ) -> Vec< Fragment >
+{
+  let mut peeled : Vec< Fragment > = Vec::with_capacity( layers );
+  let mut previous = f32::NEG_INFINITY;
+
+  while peeled.len() < layers
+  {
+    let nearest = fragments
+    .iter()
+    .filter( | fragment | fragment.depth > previous )
+    .fold( None, | nearest : Option< &Fragment >, fragment | match nearest
+    {
+      Some( nearest ) if nearest.depth <= fragment.depth => Some( nearest ),
+      _ => Some( fragment ),
+    });
+
+    match nearest
+    {
+      Some( nearest ) =>
+      {
+        previous = nearest.depth;
+        peeled.push( *nearest );
+      }
+      None => break,
+    }
+  }
+
+  peeled
+}
+
+/// Composite layers ordered nearest first with the under operator, then over the background.
+pub fn composite_front_to_back( layers : &[ Fragment ], background : Color ) -> Color
+{
+  // Premultiplied color of the layers composited so far.
+  let mut front = Color::TRANSPARENT;
+  for layer in layers
+  {
+    let Color { r, g, b, a } = layer.color;
+    let uncovered = 1.0 - front.a;
+    front = Color::new
+    (
+      front.r + uncovered * r * a,
+      front.g + uncovered * g * a,
+      front.b + uncovered * b * a,
+      front.a + uncovered * a,
+    );
+  }
+
+  let uncovered = 1.0 - front.a;
+  Color::new
+  (
+    front.r + background.r * uncovered,
+    front.g + background.g * uncovered,
+    front.b + background.b * uncovered,
+    front.a + background.a * uncovered,
+  )
+}
+
+/// Color of a pixel covered by the fragments, in any order, using `layers` peel layers.
+pub fn depth_peeled( fragments : &[ Fragment ], layers : usize, background : Color ) -> Color
+{
+  composite_front_to_back( &peel( fragments, layers ), background )
+}
+
+/// Fragments of flat colored quads covering a point of clip space, in the order of the quads.
+///
+/// Quads are the unit quad of `Mesh` placed by affine transformations to clip space, such as the
+/// view projection of an orthographic camera times the model of the mesh. Window depth is derived
+/// from clip space z the way OpenGL does.
+pub fn quad_fragments( quads : &[ ( Mat4, Color ) ], point : Vec2 ) -> Vec< Fragment >
+{
+  quads
+  .iter()
+  .filter_map( | ( transformations, color ) |
+  {
+    // Clip space xy is affine in the xy of the quad, which lies at z = 0.
+    let x_axis = transformations.x_axis.truncate().truncate();
+    let y_axis = transformations.y_axis.truncate().truncate();
+    let origin = transformations.w_axis.truncate().truncate();
+    let axes = Mat2::from_cols( x_axis, y_axis );
+    if axes.determinant().abs() <= f32::EPSILON
+    {
+      return None;
+    }
+
+    let local = axes.inverse() * ( point - origin );
+    if local.x.abs() > 1.0 || local.y.abs() > 1.0
+    {
+      return None;
+    }
+
+    let z = transformations.transform_point3( Vec3::new( local.x, local.y, 0.0 ) ).z;
+    Some( Fragment { color : *color, depth : z * 0.5 + 0.5 } )
+  })
+  .collect()
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+  use notan::math::Quat;
+  use crate::camera::Camera;
+  use crate::oit::sorted;
+  use crate::scene_file::SceneFile;
+
+  fn close( a : Color, b : Color ) -> bool
+  {
+    ( a.r - b.r ).abs() < 1e-5 && ( a.g - b.g ).abs() < 1e-5 && ( a.b - b.b ).abs() < 1e-5 && ( a.a - b.a ).abs() < 1e-5
+  }
+
+  #[ test ]
+  fn peels_nearest_first()
+  {
+    let near = Fragment { color : Color::RED, depth : 0.2 };
+    let far = Fragment { color : Color::BLUE, depth : 0.8 };
+    assert_eq!( peel( &[ far, near ], 1 ), vec![ near ] );
+    assert_eq!( peel( &[ far, near ], 4 ), vec![ near, far ] );
+    assert!( peel( &[ far, near ], 0 ).is_empty() );
+  }
+
+  /// The two icons of the scene of `main`, flat colored, drawn with the default camera.
+  #[ test ]
+  fn main_scene_matches_sorted()
+  {
+    let file = SceneFile::from_json( include_str!( "../scenes/main.json" ) ).unwrap();
+    let view_projection = Camera::default().view_projection( 1.0 );
+    let colors = [ Color::new( 1.0, 0.5, 0.0, 0.6 ), Color::new( 0.0, 0.3, 1.0, 0.7 ) ];
+    let quads : Vec< ( Mat4, Color ) > = file.meshes
+    .iter()
+    .zip( colors )
+    .map( | ( mesh, color ) |
+    {
+      let [ x, y, z, w ] = mesh.rotation;
+      let model = Mat4::from_scale_rotation_translation( mesh.scale.into(), Quat::from_xyzw( x, y, z, w ), mesh.translation.into() );
+      ( view_projection * model, color )
+    })
+    .collect();
+
+    let background = Color::new( 0.1, 0.1, 0.1, 1.0 );
+    let mut overlapping = 0;
+    for index in 0..41 * 41
+    {
+      let point = Vec2::new( ( index % 41 ) as f32, ( index / 41 ) as f32 ) * 0.01 - Vec2::new( 0.3, 0.2 );
+      let fragments = quad_fragments( &quads, point );
+      if fragments.len() == 2
+      {
+        overlapping += 1;
+      }
+      assert!( close( depth_peeled( &fragments, 2, background ), sorted( &fragments, background ) ), "at {point}" );
+    }
+    assert!( overlapping > 0, "the icons do not overlap" );
+  }
+}
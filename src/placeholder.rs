@@ -0,0 +1,56 @@
+//!
+//! Textures drawn in place of textures that are loading or failed to load.
+//!
+
+use notan::prelude::*;
+use crate::error::Error;
+
+/// Side of the placeholder textures in texels.
+const SIZE : usize = 8;
+
+/// RGBA texels of a `SIZE` by `SIZE` checkerboard of `cell` by `cell` squares.
+fn checkerboard( cell : usize, a : [ u8; 4 ], b : [ u8; 4 ] ) -> Vec< u8 >
+{
+  ( 0..SIZE * SIZE )
+  .flat_map( | index |
+  {
+    let ( x, y ) = ( index % SIZE, index / SIZE );
+    if ( x / cell + y / cell ) & 1 == 0 { a } else { b }
+  })
+  .collect()
+}
+
+/// Placeholder textures shared by the meshes of a scene.
+#[ derive( Debug ) ]
+pub( crate ) struct Placeholders
+{
+  /// Fine grey checkerboard while a texture loads.
+  pub loading : Texture,
+  /// Coarse magenta and black checkerboard once a texture failed.
+  pub failed : Texture,
+}
+
+impl Placeholders
+{
+  pub fn new( gfx : &mut Graphics ) -> Result< Self, Error >
+  {
+    let loading = Self::loading( gfx )?;
+    let failed = Self::texture( gfx, &checkerboard( SIZE / 2, [ 255, 0, 255, 255 ], [ 0, 0, 0, 255 ] ) )?;
+    Ok( Self { loading, failed } )
+  }
+
+  /// Placeholder of a loading texture on its own, for meshes outside of a scene.
+  pub fn loading( gfx : &mut Graphics ) -> Result< Texture, Error >
+  {
+    Self::texture( gfx, &checkerboard( 1, [ 160, 160, 160, 255 ], [ 200, 200, 200, 255 ] ) )
+  }
+
+  fn texture( gfx : &mut Graphics, texels : &[ u8 ] ) -> Result< Texture, Error >
+  {
+    gfx
+    .create_texture()
+    .from_bytes( texels, SIZE as i32, SIZE as i32 )
+    .with_filter( TextureFilter::Nearest, TextureFilter::Nearest )
+    .build().map_err( Error::texture )
+  }
+}
@@ -0,0 +1,147 @@
+//!
+//! Sampling options of the textures of meshes : filtering, wrap modes and mipmaps, and the image
+//! files textures are created from with them.
+//!
+
+use notan::prelude::*;
+use serde::{ Deserialize, Serialize };
+use crate::error::Error;
+use crate::AlphaMode;
+
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
+#[ serde( rename_all = "snake_case" ) ]
+pub enum Filter
+{
+  Linear,
+  Nearest,
+}
+
+impl From< Filter > for TextureFilter
+{
+  fn from( filter : Filter ) -> Self
+  {
+    match filter
+    {
+      Filter::Linear => TextureFilter::Linear,
+      Filter::Nearest => TextureFilter::Nearest,
+    }
+  }
+}
+
+/// What is sampled outside of `0..=1` texture coordinates.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
+#[ serde( rename_all = "snake_case" ) ]
+pub enum Wrap
+{
+  /// The texels of the edge.
+  Clamp,
+  Repeat,
+}
+
+impl From< Wrap > for TextureWrap
+{
+  fn from( wrap : Wrap ) -> Self
+  {
+    match wrap
+    {
+      Wrap::Clamp => TextureWrap::Clamp,
+      Wrap::Repeat => TextureWrap::Repeat,
+    }
+  }
+}
+
+/// Encoded PNG or JPEG file, kept as loaded so that textures are created from it with a sampling.
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+pub struct ImageFile( pub Vec< u8 > );
+
+fn parse_image_file( _id : &str, bytes : Vec< u8 > ) -> Result< ImageFile, String >
+{
+  Ok( ImageFile( bytes ) )
+}
+
+/// Loader of PNG and JPEG files as `ImageFile`, in place of the texture loader of notan which
+/// creates textures with a fixed sampling. Scenes load their textures with it.
+pub fn image_loader() -> AssetLoader
+{
+  AssetLoader::new()
+  .use_parser( parse_image_file )
+  .extensions( &[ "png", "jpg", "jpeg" ] )
+}
+
+/// How the texture of a mesh is sampled. The default is the default of notan textures : nearest
+/// filtering, clamped, without mipmaps.
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
+#[ serde( default, deny_unknown_fields ) ]
+pub struct Sampling
+{
+  /// Filter of texels smaller than a pixel.
+  pub min_filter : Filter,
+  /// Filter of texels larger than a pixel.
+  pub mag_filter : Filter,
+  pub wrap_x : Wrap,
+  pub wrap_y : Wrap,
+  /// Not supported by notan 0.5 : scene files asking for them are rejected, and `Mesh::set_sampling`
+  /// clears them with a warning.
+  pub mipmaps : bool,
+}
+
+impl Default for Sampling
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      min_filter : Filter::Nearest,
+      mag_filter : Filter::Nearest,
+      wrap_x : Wrap::Clamp,
+      wrap_y : Wrap::Clamp,
+      mipmaps : false,
+    }
+  }
+}
+
+impl Sampling
+{
+  pub fn filter( mut self, min_filter : Filter, mag_filter : Filter ) -> Self
+  {
+    self.min_filter = min_filter;
+    self.mag_filter = mag_filter;
+    self
+  }
+
+  pub fn wrap( mut self, wrap_x : Wrap, wrap_y : Wrap ) -> Self
+  {
+    self.wrap_x = wrap_x;
+    self.wrap_y = wrap_y;
+    self
+  }
+
+  pub fn mipmaps( mut self, mipmaps : bool ) -> Self
+  {
+    self.mipmaps = mipmaps;
+    self
+  }
+
+  /// Whether it is the default sampling, which scene files leave out.
+  pub fn is_default( &self ) -> bool
+  {
+    *self == Self::default()
+  }
+
+  /// Texture of the image file `file` at `path` sampled this way, its texels premultiplied by
+  /// their alpha for `AlphaMode::Premultiplied` so that filtering never bleeds hidden colors.
+  pub( crate ) fn texture( &self, gfx : &mut Graphics, path : &str, file : &ImageFile, alpha_mode : AlphaMode ) -> Result< Texture, Error >
+  {
+    let builder = gfx
+    .create_texture()
+    .from_image( &file.0 )
+    .with_filter( self.min_filter.into(), self.mag_filter.into() )
+    .with_wrap( self.wrap_x.into(), self.wrap_y.into() );
+    match alpha_mode
+    {
+      AlphaMode::Straight => builder,
+      AlphaMode::Premultiplied => builder.with_premultiplied_alpha(),
+    }
+    .build().map_err( | message | Error::Asset { path : path.to_string(), message } )
+  }
+}
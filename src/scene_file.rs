@@ -0,0 +1,256 @@
+//!
+//! Scene files : the meshes of a scene described in JSON.
+//!
+
+use serde::{ Deserialize, Serialize };
+use crate::error::Error;
+use crate::graph::Graph;
+use crate::lib::RenderClass;
+use crate::sampling::Sampling;
+
+/// Mesh of a scene file. Only `texture` and `class` are required.
+#[ derive( Debug, Clone, PartialEq, Serialize, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+pub struct MeshEntry
+{
+  /// Name of a texture of the manifest of the scene, or path relative to the served directory.
+  pub texture : String,
+  /// How the mesh blends with what is behind it.
+  pub class : RenderClass,
+  #[ serde( default ) ]
+  pub translation : [ f32; 3 ],
+  /// Quaternion as `[ x, y, z, w ]`, normalized when loaded.
+  #[ serde( default = "identity" ) ]
+  pub rotation : [ f32; 4 ],
+  #[ serde( default = "one" ) ]
+  pub scale : [ f32; 3 ],
+  #[ serde( default = "opaque" ) ]
+  pub opacity : f32,
+  #[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+  pub alpha_cutoff : Option< f32 >,
+  /// Filtering, wrap modes and mipmaps of the texture, omitted fields take their default.
+  #[ serde( default, skip_serializing_if = "Sampling::is_default" ) ]
+  pub sampling : Sampling,
+  /// Part of the texture drawn as `[ x, y, width, height ]` in uvs from the top left corner, the
+  /// region or the whole texture `texture` names by default.
+  #[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+  pub uv_rect : Option< [ f32; 4 ] >,
+  /// Mirror the part of the texture drawn horizontally.
+  #[ serde( default, skip_serializing_if = "is_false" ) ]
+  pub flip_x : bool,
+  /// Mirror the part of the texture drawn vertically.
+  #[ serde( default, skip_serializing_if = "is_false" ) ]
+  pub flip_y : bool,
+  /// Index of the parent in the meshes of the file, the transform is relative to it.
+  #[ serde( default, skip_serializing_if = "Option::is_none" ) ]
+  pub parent : Option< usize >,
+  /// Meshes are added to the scene, and drawn among meshes at the same depth, by increasing order
+  /// then in the order of the file.
+  #[ serde( default, skip_serializing_if = "is_zero" ) ]
+  pub order : i32,
+}
+
+fn identity() -> [ f32; 4 ]
+{
+  [ 0.0, 0.0, 0.0, 1.0 ]
+}
+
+fn one() -> [ f32; 3 ]
+{
+  [ 1.0; 3 ]
+}
+
+fn opaque() -> f32
+{
+  1.0
+}
+
+fn is_zero( order : &i32 ) -> bool
+{
+  *order == 0
+}
+
+fn is_false( flag : &bool ) -> bool
+{
+  !*flag
+}
+
+/// Meshes of a scene.
+#[ derive( Debug, Clone, Default, PartialEq, Serialize, Deserialize ) ]
+#[ serde( deny_unknown_fields ) ]
+pub struct SceneFile
+{
+  pub meshes : Vec< MeshEntry >,
+}
+
+impl SceneFile
+{
+  /// Parse and validate a scene file.
+  pub fn from_json( json : &str ) -> Result< Self, Error >
+  {
+    let file : Self = serde_json::from_str( json ).map_err( | error | Error::Parse( error.to_string() ) )?;
+    file.validate()?;
+    Ok( file )
+  }
+
+  pub fn to_json( &self ) -> String
+  {
+    serde_json::to_string_pretty( self ).unwrap()
+  }
+
+  /// Check the values the JSON types allow but a scene does not, the error names the first invalid field.
+  pub fn validate( &self ) -> Result< (), Error >
+  {
+    let invalid = | mesh : usize, field : &'static str, message : &str | Error::Invalid { mesh, field, message : message.to_string() };
+
+    let mut graph = Graph::default();
+    for _ in &self.meshes
+    {
+      graph.push();
+    }
+
+    for ( index, mesh ) in self.meshes.iter().enumerate()
+    {
+      if mesh.texture.is_empty()
+      {
+        return Err( invalid( index, "texture", "is empty" ) );
+      }
+      if !mesh.translation.iter().all( | value | value.is_finite() )
+      {
+        return Err( invalid( index, "translation", "is not finite" ) );
+      }
+      if !mesh.rotation.iter().all( | value | value.is_finite() ) || mesh.rotation.iter().all( | value | *value == 0.0 )
+      {
+        return Err( invalid( index, "rotation", "is not a finite non zero quaternion" ) );
+      }
+      // A zero scale hides the mesh, as animations do.
+      if !mesh.scale.iter().all( | value | value.is_finite() )
+      {
+        return Err( invalid( index, "scale", "is not finite" ) );
+      }
+      if !( 0.0..=1.0 ).contains( &mesh.opacity )
+      {
+        return Err( invalid( index, "opacity", "is not in 0..=1" ) );
+      }
+      if mesh.alpha_cutoff.is_some_and( | cutoff | !( 0.0..=1.0 ).contains( &cutoff ) )
+      {
+        return Err( invalid( index, "alpha_cutoff", "is not in 0..=1" ) );
+      }
+      if mesh.uv_rect.is_some_and( | uv_rect | !uv_rect.iter().all( | value | value.is_finite() ) || uv_rect[ 2 ] <= 0.0 || uv_rect[ 3 ] <= 0.0 )
+      {
+        return Err( invalid( index, "uv_rect", "is not finite with a positive width and height" ) );
+      }
+      if mesh.sampling.mipmaps
+      {
+        return Err( invalid( index, "sampling", "asks for mipmaps, notan 0.5 can not generate them" ) );
+      }
+      if let Some( parent ) = mesh.parent
+      {
+        if parent >= self.meshes.len()
+        {
+          return Err( invalid( index, "parent", "is not the index of a mesh" ) );
+        }
+        if !graph.set_parent( index, Some( parent ) )
+        {
+          return Err( invalid( index, "parent", "makes the mesh its own ancestor" ) );
+        }
+      }
+    }
+    Ok( () )
+  }
+
+  /// Indices of the meshes in the order they are added to a scene.
+  pub fn order( &self ) -> Vec< usize >
+  {
+    let mut order : Vec< usize > = ( 0..self.meshes.len() ).collect();
+    order.sort_by_key( | &index | self.meshes[ index ].order );
+    order
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn entry( texture : &str ) -> MeshEntry
+  {
+    serde_json::from_str( &format!( r#"{{ "texture" : "{texture}", "class" : "opaque" }}"# ) ).unwrap()
+  }
+
+  fn invalid( file : &SceneFile ) -> Option< ( usize, &'static str ) >
+  {
+    match file.validate()
+    {
+      Err( Error::Invalid { mesh, field, .. } ) => Some( ( mesh, field ) ),
+      _ => None,
+    }
+  }
+
+  #[ test ]
+  fn invalid_entries_are_named()
+  {
+    let mut file = SceneFile { meshes : vec![ entry( "a.png" ), entry( "b.png" ), entry( "c.png" ) ] };
+    assert_eq!( file.validate(), Ok( () ) );
+
+    file.meshes[ 1 ].alpha_cutoff = Some( 1.5 );
+    assert_eq!( invalid( &file ), Some( ( 1, "alpha_cutoff" ) ) );
+    file.meshes[ 1 ].alpha_cutoff = None;
+
+    file.meshes[ 2 ].translation[ 0 ] = f32::NAN;
+    assert_eq!( invalid( &file ), Some( ( 2, "translation" ) ) );
+    file.meshes[ 2 ].translation[ 0 ] = 0.0;
+
+    file.meshes[ 2 ].parent = Some( 3 );
+    assert_eq!( invalid( &file ), Some( ( 2, "parent" ) ) );
+
+    // Scaled to zero is hidden, not invalid.
+    file.meshes[ 2 ].parent = None;
+    file.meshes[ 0 ].scale = [ 0.0; 3 ];
+    assert_eq!( file.validate(), Ok( () ) );
+  }
+
+  #[ test ]
+  fn parent_cycles_are_invalid()
+  {
+    let mut file = SceneFile { meshes : vec![ entry( "a.png" ), entry( "b.png" ), entry( "c.png" ) ] };
+    file.meshes[ 0 ].parent = Some( 2 );
+    file.meshes[ 1 ].parent = Some( 0 );
+    file.meshes[ 2 ].parent = Some( 1 );
+    assert_eq!( invalid( &file ), Some( ( 2, "parent" ) ) );
+
+    file.meshes[ 0 ].parent = Some( 0 );
+    assert_eq!( invalid( &file ), Some( ( 0, "parent" ) ) );
+  }
+
+  #[ test ]
+  fn files_round_trip()
+  {
+    let mut file = SceneFile { meshes : vec![ entry( "a.png" ), entry( "atlas:b" ) ] };
+    let mesh = &mut file.meshes[ 1 ];
+    mesh.class = RenderClass::Translucent;
+    mesh.translation = [ 1.0, -2.0, 0.5 ];
+    mesh.rotation = [ 0.0, 0.0, 0.6, 0.8 ];
+    mesh.scale = [ 2.0, 0.0, 1.0 ];
+    mesh.opacity = 0.25;
+    mesh.alpha_cutoff = Some( 0.1 );
+    mesh.uv_rect = Some( [ 0.5, 0.0, 0.5, 0.25 ] );
+    mesh.flip_x = true;
+    mesh.parent = Some( 0 );
+    mesh.order = -1;
+    assert_eq!( SceneFile::from_json( &file.to_json() ), Ok( file ) );
+  }
+
+  #[ test ]
+  fn sampling_round_trips()
+  {
+    use crate::sampling::{ Filter, Wrap };
+
+    let mut file = SceneFile { meshes : vec![ entry( "a.png" ), entry( "b.png" ) ] };
+    file.meshes[ 0 ].sampling = Sampling::default().filter( Filter::Linear, Filter::Nearest ).wrap( Wrap::Repeat, Wrap::Clamp );
+    let json = file.to_json();
+    // Only the non default sampling is written.
+    assert_eq!( json.matches( "sampling" ).count(), 1 );
+    assert_eq!( SceneFile::from_json( &json ), Ok( file ) );
+  }
+}
@@ -0,0 +1,73 @@
+//!
+//! Quad covering the whole target, used to composite offscreen targets.
+//!
+
+use notan::prelude::*;
+use crate::error::Error;
+
+pub( crate ) const VERT_SCREEN : ShaderSource< '_ > = notan::vertex_shader!
+{
+  r#"
+  #version 450
+
+  layout( location = 0 ) in vec2 a_pos;
+  layout( location = 0 ) out vec2 v_uv;
+
+  void main()
+  {
+    v_uv = a_pos * 0.5 + 0.5;
+    gl_Position = vec4( a_pos, 0.0, 1.0 );
+  }
+  "#
+};
+
+#[ derive( Debug ) ]
+pub( crate ) struct ScreenQuad
+{
+  vertex_buffer : Buffer,
+  index_buffer : Buffer,
+}
+
+impl ScreenQuad
+{
+  pub fn new( gfx : &mut Graphics ) -> Result< Self, Error >
+  {
+    let vertex_buffer = gfx
+    .create_vertex_buffer()
+    .with_info( &Self::vertex_info() )
+    .with_data( &[ -1.0, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0, 1.0 ] )
+    .build().map_err( Error::buffer )?;
+    let index_buffer = gfx
+    .create_index_buffer()
+    .with_data( &[ 0, 1, 2, 2, 3, 0 ] )
+    .build().map_err( Error::buffer )?;
+
+    Ok( Self
+    {
+      vertex_buffer,
+      index_buffer,
+    })
+  }
+
+  pub fn vertex_info() -> VertexInfo
+  {
+    VertexInfo::new()
+    .attr( 0, VertexFormat::Float32x2 ) // positions
+  }
+
+  /// Pipeline drawing the quad with `VERT_SCREEN` and the given fragment shader.
+  pub fn pipeline< 'a >( gfx : &'a mut Graphics, fragment : &'a ShaderSource< 'a > ) -> PipelineBuilder< 'a, 'a >
+  {
+    gfx
+    .create_pipeline()
+    .from( &VERT_SCREEN, fragment )
+    .with_vertex_info( &Self::vertex_info() )
+  }
+
+  /// Record the draw of the quad with the pipeline and textures already set on the renderer.
+  pub fn draw( &self, renderer : &mut Renderer )
+  {
+    renderer.bind_buffers( &[ &self.vertex_buffer, &self.index_buffer ] );
+    renderer.draw( 0, 6 );
+  }
+}
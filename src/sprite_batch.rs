@@ -0,0 +1,202 @@
+//!
+//! Batching of textured quads on the CPU, for backends without instancing.
+//!
+
+use notan::prelude::Color;
+use notan::math::{ Mat4, Rect, Vec3 };
+
+/// Floats per vertex : position, uv and color.
+pub const VERTEX_FLOATS : usize = 9;
+
+/// Quad of a mesh to batch.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Sprite
+{
+  pub transformations : Mat4,
+  /// Part of the texture drawn, in uvs.
+  pub uv_rect : Rect,
+  /// Straight alpha color the texture is multiplied by.
+  pub color : Color,
+}
+
+impl Sprite
+{
+  /// Vertices of the quad in world space, in the order of `quad_indices`.
+  ///
+  /// Same corners and uvs as `Shape::Quad`.
+  pub fn vertices( &self ) -> [ f32; VERTEX_FLOATS * 4 ]
+  {
+    let corners = [ ( -1.0, -1.0 ), ( -1.0, 1.0 ), ( 1.0, 1.0 ), ( 1.0, -1.0 ) ];
+    let Rect { x, y, width, height } = self.uv_rect;
+    let Color { r, g, b, a } = self.color;
+
+    let mut vertices = [ 0.0; VERTEX_FLOATS * 4 ];
+    for ( vertex, ( u, v ) ) in vertices.chunks_exact_mut( VERTEX_FLOATS ).zip( corners )
+    {
+      let position = self.transformations.transform_point3( Vec3::new( u, v, 0.0 ) );
+      let uv = [ x + ( u + 1.0 ) * 0.5 * width, y + ( 1.0 - v ) * 0.5 * height ];
+      vertex.copy_from_slice( &[ position.x, position.y, position.z, uv[ 0 ], uv[ 1 ], r, g, b, a ] );
+    }
+    vertices
+  }
+}
+
+/// Indices of `quads` quads, two triangles each.
+pub fn quad_indices( quads : usize ) -> Vec< u32 >
+{
+  ( 0..quads as u32 )
+  .flat_map( | quad | [ 0, 1, 2, 2, 3, 0 ].map( | index | quad * 4 + index ) )
+  .collect()
+}
+
+/// Quads sharing a texture, ready to upload.
+#[ derive( Debug, Clone, PartialEq ) ]
+pub struct Batch
+{
+  pub texture : u64,
+  pub vertices : Vec< f32 >,
+}
+
+impl Batch
+{
+  pub fn quads( &self ) -> usize
+  {
+    self.vertices.len() / ( VERTEX_FLOATS * 4 )
+  }
+}
+
+/// Collects quads sharing a texture into batches of bounded size.
+#[ derive( Debug, Clone ) ]
+pub struct SpriteBatcher
+{
+  capacity : usize,
+  texture : Option< u64 >,
+  vertices : Vec< f32 >,
+  flushes : usize,
+}
+
+impl SpriteBatcher
+{
+  /// Batcher of up to `capacity` quads per batch.
+  pub fn new( capacity : usize ) -> Self
+  {
+    let capacity = capacity.max( 1 );
+    Self
+    {
+      capacity,
+      texture : None,
+      vertices : Vec::with_capacity( capacity * VERTEX_FLOATS * 4 ),
+      flushes : 0,
+    }
+  }
+
+  pub fn capacity( &self ) -> usize
+  {
+    self.capacity
+  }
+
+  /// Add a quad with `texture`. The current batch is flushed first and returned if its texture
+  /// differs or it is full.
+  pub fn push( &mut self, texture : u64, sprite : &Sprite ) -> Option< Batch >
+  {
+    let full = self.vertices.len() >= self.capacity * VERTEX_FLOATS * 4;
+    let flushed = if full || self.texture != Some( texture ) { self.flush() } else { None };
+
+    self.texture = Some( texture );
+    self.vertices.extend_from_slice( &sprite.vertices() );
+    flushed
+  }
+
+  /// Take the current batch, `None` if it is empty.
+  pub fn flush( &mut self ) -> Option< Batch >
+  {
+    let texture = self.texture.take()?;
+    if self.vertices.is_empty()
+    {
+      return None;
+    }
+
+    self.flushes += 1;
+    let vertices = std::mem::replace( &mut self.vertices, Vec::with_capacity( self.capacity * VERTEX_FLOATS * 4 ) );
+    Some( Batch { texture, vertices } )
+  }
+
+  /// Number of batches flushed so far.
+  pub fn flushes( &self ) -> usize
+  {
+    self.flushes
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+
+  fn sprite() -> Sprite
+  {
+    Sprite
+    {
+      transformations : Mat4::IDENTITY,
+      uv_rect : Rect { x : 0.0, y : 0.0, width : 1.0, height : 1.0 },
+      color : Color::WHITE,
+    }
+  }
+
+  #[ test ]
+  fn vertices_match_the_quad()
+  {
+    let sprite = Sprite
+    {
+      transformations : Mat4::from_translation( Vec3::new( 1.0, 2.0, 3.0 ) ),
+      uv_rect : Rect { x : 0.5, y : 0.25, width : 0.5, height : 0.25 },
+      color : Color::new( 0.1, 0.2, 0.3, 0.4 ),
+    };
+    let vertices = sprite.vertices();
+    let vertex = | index : usize | &vertices[ index * VERTEX_FLOATS..( index + 1 ) * VERTEX_FLOATS ];
+    // Bottom left, then top left : v grows downward.
+    assert_eq!( vertex( 0 ), &[ 0.0, 1.0, 3.0, 0.5, 0.5, 0.1, 0.2, 0.3, 0.4 ] );
+    assert_eq!( vertex( 1 ), &[ 0.0, 3.0, 3.0, 0.5, 0.25, 0.1, 0.2, 0.3, 0.4 ] );
+    assert_eq!( vertex( 2 ), &[ 2.0, 3.0, 3.0, 1.0, 0.25, 0.1, 0.2, 0.3, 0.4 ] );
+    assert_eq!( vertex( 3 ), &[ 2.0, 1.0, 3.0, 1.0, 0.5, 0.1, 0.2, 0.3, 0.4 ] );
+  }
+
+  #[ test ]
+  fn indices_offset_by_quad()
+  {
+    assert_eq!( quad_indices( 2 ), vec![ 0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4 ] );
+    assert!( quad_indices( 0 ).is_empty() );
+  }
+
+  #[ test ]
+  fn texture_change_flushes()
+  {
+    let mut batcher = SpriteBatcher::new( 8 );
+    assert_eq!( batcher.push( 1, &sprite() ), None );
+    assert_eq!( batcher.push( 1, &sprite() ), None );
+    let batch = batcher.push( 2, &sprite() ).unwrap();
+    assert_eq!( ( batch.texture, batch.quads() ), ( 1, 2 ) );
+    let batch = batcher.flush().unwrap();
+    assert_eq!( ( batch.texture, batch.quads() ), ( 2, 1 ) );
+    assert_eq!( batcher.flushes(), 2 );
+  }
+
+  #[ test ]
+  fn full_batch_flushes()
+  {
+    let mut batcher = SpriteBatcher::new( 2 );
+    let mut batches : Vec< Batch > = ( 0..5 ).filter_map( | _ | batcher.push( 1, &sprite() ) ).collect();
+    batches.extend( batcher.flush() );
+    let quads : Vec< usize > = batches.iter().map( Batch::quads ).collect();
+    assert_eq!( quads, vec![ 2, 2, 1 ] );
+  }
+
+  #[ test ]
+  fn empty_flush_is_none()
+  {
+    let mut batcher = SpriteBatcher::new( 0 );
+    assert_eq!( batcher.capacity(), 1 );
+    assert_eq!( batcher.flush(), None );
+    assert_eq!( batcher.flushes(), 0 );
+  }
+}
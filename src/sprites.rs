@@ -0,0 +1,232 @@
+//!
+//! Meshes drawn through the sprite batcher, see `sprite_batch` for the batching.
+//!
+
+use std::collections::hash_map::{ Entry, HashMap };
+use notan::prelude::*;
+use notan::log;
+use crate::draw_list::{ DrawList, FrameStats };
+use crate::error::Error;
+use crate::lib::{ bind_uniform_blocks, AlphaMode, Coverage, Mesh, View, FRAG_DITHER, FRAG_DITHER_PREMULTIPLIED };
+use crate::sprite_batch::{ quad_indices, Batch, SpriteBatcher };
+
+const VERT_SPRITE : ShaderSource< '_ > = notan::vertex_shader!
+{
+  r#"
+  #version 450
+
+  layout( location = 0 ) in vec3 a_pos;
+  layout( location = 1 ) in vec2 a_uv;
+  layout( location = 2 ) in vec4 a_color;
+  layout( location = 0 ) out vec2 v_uv;
+  layout( location = 1 ) out vec4 v_color;
+
+  layout( set = 0, binding = 0 ) uniform Camera
+  {
+    mat4 view_projection;
+  };
+
+  void main()
+  {
+    v_uv = a_uv;
+    v_color = a_color;
+    gl_Position = view_projection * vec4( a_pos, 1.0 );
+  }
+  "#
+};
+
+const FRAG_SPRITE : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 1 ) in vec4 v_color;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  layout( set = 3, binding = 0 ) uniform SpriteMaterial
+  {
+    float premultiplied;
+    float cutoff;
+  };
+
+  void main()
+  {
+    color = texture( u_texture, v_uv ) * vec4( v_color.rgb * mix( 1.0, v_color.a, premultiplied ), v_color.a );
+    if( color.a < cutoff )
+    {
+      discard;
+    }
+  }
+  "#
+};
+
+fn vertex_info() -> VertexInfo
+{
+  VertexInfo::new()
+  .attr( 0, VertexFormat::Float32x3 ) // positions
+  .attr( 1, VertexFormat::Float32x2 ) // uvs
+  .attr( 2, VertexFormat::Float32x4 ) // colors
+}
+
+/// Pipeline, premultiplied and cutoff shared by the quads of a batch.
+type State = ( usize, [ f32; 2 ] );
+
+/// Draws meshes as quads transformed on the CPU into one dynamic vertex buffer per texture, a
+/// renderer per batch. Batches are flushed when the texture, pipeline or material changes, or when
+/// they are full.
+#[ derive( Debug ) ]
+pub( crate ) struct Sprites
+{
+  /// Pipelines of the meshes, indexed by `RenderClass::pipeline_index`.
+  pipelines : [ Pipeline; 3 ],
+  material : Buffer,
+  index_buffer : Buffer,
+  vertex_buffers : HashMap< u64, Buffer >,
+  batcher : SpriteBatcher,
+}
+
+impl Sprites
+{
+  /// Sprites drawn in batches of up to `capacity` quads.
+  pub fn new( gfx : &mut Graphics, coverage : Coverage, alpha_mode : AlphaMode, capacity : usize ) -> Result< Self, Error >
+  {
+    let depth_write = DepthStencil
+    {
+      write : true,
+      compare : CompareMode::Less,
+    };
+    let depth_test = DepthStencil
+    {
+      write : false,
+      compare : CompareMode::Less,
+    };
+
+    let opaque_pipeline = Self::pipeline( gfx, &FRAG_SPRITE )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    let alpha_tested_fragment = match ( coverage, alpha_mode )
+    {
+      ( Coverage::AlphaTest, _ ) => &FRAG_SPRITE,
+      ( Coverage::Dither, AlphaMode::Straight ) => &FRAG_DITHER,
+      ( Coverage::Dither, AlphaMode::Premultiplied ) => &FRAG_DITHER_PREMULTIPLIED,
+    };
+    let alpha_tested_pipeline = Self::pipeline( gfx, alpha_tested_fragment )
+    .with_depth_stencil( depth_write )
+    .build().map_err( Error::pipeline )?;
+    let translucent_pipeline = match alpha_mode
+    {
+      AlphaMode::Straight => Self::pipeline( gfx, &FRAG_SPRITE )
+      .with_color_blend( BlendMode::NORMAL ),
+      AlphaMode::Premultiplied => Self::pipeline( gfx, &FRAG_SPRITE )
+      .with_color_blend( BlendMode::OVER )
+      .with_alpha_blend( BlendMode::OVER ),
+    }
+    .with_depth_stencil( depth_test )
+    .build().map_err( Error::pipeline )?;
+    bind_uniform_blocks( gfx, &[ &opaque_pipeline, &alpha_tested_pipeline, &translucent_pipeline ] )?;
+
+    let material = gfx.create_uniform_buffer( 3, "SpriteMaterial" )
+    .build().map_err( Error::buffer )?;
+    let batcher = SpriteBatcher::new( capacity );
+    let index_buffer = gfx
+    .create_index_buffer()
+    .with_data( &quad_indices( batcher.capacity() ) )
+    .build().map_err( Error::buffer )?;
+
+    Ok( Self
+    {
+      pipelines : [ opaque_pipeline, alpha_tested_pipeline, translucent_pipeline ],
+      material,
+      index_buffer,
+      vertex_buffers : HashMap::new(),
+      batcher,
+    })
+  }
+
+  fn pipeline< 'a >( gfx : &'a mut Graphics, fragment : &'a ShaderSource< 'a > ) -> PipelineBuilder< 'a, 'a >
+  {
+    gfx
+    .create_pipeline()
+    .from( &VERT_SPRITE, fragment )
+    .with_vertex_info( &vertex_info() )
+    .with_texture_location( 0, "u_texture" )
+  }
+
+  /// Clear the screen and draw the meshes in the order of the list.
+  pub fn render( &mut self, gfx : &mut Graphics, meshes : &[ Mesh ], list : &DrawList, view : &View< '_ >, clear : &ClearOptions ) -> FrameStats
+  {
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( Some( clear ) );
+    renderer.end();
+    gfx.render( &renderer );
+
+    let mut stats = FrameStats::default();
+    let mut textures = HashMap::new();
+    let mut state : Option< State > = None;
+
+    for draw in list.draws()
+    {
+      let mesh = &meshes[ draw.mesh ];
+      let texture = mesh.drawn_texture();
+      let id = texture.id();
+      textures.insert( id, texture );
+
+      let next = ( draw.pipeline, mesh.sprite_material() );
+      if state != Some( next )
+      {
+        if let ( Some( batch ), Some( state ) ) = ( self.batcher.flush(), state )
+        {
+          self.draw( gfx, batch, state, &textures, view, &mut stats ).unwrap_or_else( skipped );
+        }
+        state = Some( next );
+      }
+      if let Some( batch ) = self.batcher.push( id, &mesh.sprite() )
+      {
+        self.draw( gfx, batch, next, &textures, view, &mut stats ).unwrap_or_else( skipped );
+      }
+    }
+
+    if let ( Some( batch ), Some( state ) ) = ( self.batcher.flush(), state )
+    {
+      self.draw( gfx, batch, state, &textures, view, &mut stats ).unwrap_or_else( skipped );
+    }
+
+    stats
+  }
+
+  /// Upload a batch to the vertex buffer of its texture and draw it.
+  fn draw( &mut self, gfx : &mut Graphics, batch : Batch, ( pipeline, material ) : State, textures : &HashMap< u64, Texture >, view : &View< '_ >, stats : &mut FrameStats ) -> Result< (), Error >
+  {
+    let vertex_buffer = match self.vertex_buffers.entry( batch.texture )
+    {
+      Entry::Occupied( entry ) => entry.into_mut(),
+      Entry::Vacant( entry ) => entry.insert( gfx.create_vertex_buffer().with_info( &vertex_info() ).build().map_err( Error::buffer )? ),
+    };
+    gfx.set_buffer_data( vertex_buffer, &batch.vertices );
+    gfx.set_buffer_data( &self.material, &[ material[ 0 ], material[ 1 ], 0.0, 0.0 ] );
+
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( None );
+    renderer.set_pipeline( &self.pipelines[ pipeline ] );
+    view.bind( &mut renderer );
+    renderer.bind_texture( 0, &textures[ &batch.texture ] );
+    renderer.bind_buffers( &[ vertex_buffer, &self.index_buffer, &self.material ] );
+    renderer.draw( 0, batch.quads() as i32 * 6 );
+    renderer.end();
+    gfx.render( &renderer );
+
+    stats.draw_calls += 1;
+    stats.instances += batch.quads();
+    stats.pipeline_changes += 1;
+    stats.texture_changes += 1;
+    Ok( () )
+  }
+}
+
+/// Log a batch that could not be drawn, the frame goes on without it.
+fn skipped( error : Error )
+{
+  log::error!( "{error}, a batch of meshes is not drawn" );
+}
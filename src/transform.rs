@@ -0,0 +1,164 @@
+//!
+//! Placement of a mesh decomposed into translation, rotation and scale.
+//!
+
+use notan::math::{ EulerRot, Mat3, Mat4, Quat, Vec3 };
+
+/// Scale, then rotation, then translation.
+#[ derive( Debug, Clone, Copy, PartialEq ) ]
+pub struct Transform
+{
+  pub translation : Vec3,
+  pub rotation : Quat,
+  pub scale : Vec3,
+}
+
+impl Default for Transform
+{
+  fn default() -> Self
+  {
+    Self
+    {
+      translation : Vec3::ZERO,
+      rotation : Quat::IDENTITY,
+      scale : Vec3::ONE,
+    }
+  }
+}
+
+impl Transform
+{
+  /// Transform without rotation.
+  pub fn new( scale : impl Into< Vec3 >, translation : impl Into< Vec3 > ) -> Self
+  {
+    Self
+    {
+      translation : translation.into(),
+      rotation : Quat::IDENTITY,
+      scale : scale.into(),
+    }
+  }
+
+  /// Decompose a matrix made of a scale, a rotation and a translation. Shear, from non uniform
+  /// scales of rotated parents, is lost.
+  pub fn from_matrix( matrix : Mat4 ) -> Self
+  {
+    let ( scale, rotation, translation ) = matrix.to_scale_rotation_translation();
+    Self
+    {
+      translation,
+      rotation,
+      scale,
+    }
+  }
+
+  /// Rotation from Euler angles in radians, applied in the order of `order`.
+  pub fn set_euler( &mut self, order : EulerRot, a : f32, b : f32, c : f32 )
+  {
+    self.rotation = Quat::from_euler( order, a, b, c );
+  }
+
+  /// Euler angles in radians of the rotation, in the order of `order`.
+  pub fn euler( &self, order : EulerRot ) -> ( f32, f32, f32 )
+  {
+    self.rotation.to_euler( order )
+  }
+
+  /// Rotation of `angle` radians around `axis`, which does not need to be normalized. A zero axis
+  /// leaves the rotation as is.
+  pub fn set_axis_angle( &mut self, axis : Vec3, angle : f32 )
+  {
+    let axis = axis.normalize_or_zero();
+    if axis != Vec3::ZERO
+    {
+      self.rotation = Quat::from_axis_angle( axis, angle );
+    }
+  }
+
+  /// Rotate so that the front of the mesh, its +z side, faces `target`, with its +y side towards
+  /// `up`. The rotation is left as is when `target` is at the translation or in the direction of `up`.
+  pub fn look_at( &mut self, target : Vec3, up : Vec3 )
+  {
+    let forward = ( target - self.translation ).normalize_or_zero();
+    let right = up.cross( forward ).normalize_or_zero();
+    if forward == Vec3::ZERO || right == Vec3::ZERO
+    {
+      return;
+    }
+
+    let up = forward.cross( right );
+    self.rotation = Quat::from_mat3( &Mat3::from_cols( right, up, forward ) );
+  }
+
+  /// Matrix from the local space of the mesh to the world.
+  pub fn matrix( &self ) -> Mat4
+  {
+    Mat4::from_scale_rotation_translation( self.scale, self.rotation, self.translation )
+  }
+}
+
+#[ cfg( test ) ]
+mod tests
+{
+  use super::*;
+  use std::f32::consts::FRAC_PI_2;
+
+  fn close( a : Vec3, b : Vec3 ) -> bool
+  {
+    a.abs_diff_eq( b, 1e-5 )
+  }
+
+  #[ test ]
+  fn look_at_faces_the_target()
+  {
+    let mut transform = Transform::new( Vec3::ONE, Vec3::new( 1.0, 2.0, 3.0 ) );
+    transform.look_at( Vec3::new( 5.0, 2.0, 3.0 ), Vec3::Y );
+    assert!( close( transform.rotation * Vec3::Z, Vec3::X ) );
+    assert!( close( transform.rotation * Vec3::Y, Vec3::Y ) );
+    assert!( close( transform.rotation * Vec3::X, -Vec3::Z ) );
+
+    // The up side leans towards `up` when it is not perpendicular to the front.
+    transform.look_at( Vec3::new( 1.0, 2.0, 10.0 ), Vec3::new( 0.0, 1.0, 1.0 ) );
+    assert!( close( transform.rotation * Vec3::Z, Vec3::Z ) );
+    assert!( close( transform.rotation * Vec3::Y, Vec3::Y ) );
+  }
+
+  #[ test ]
+  fn look_at_degenerate_targets_keep_the_rotation()
+  {
+    let mut transform = Transform::new( Vec3::ONE, Vec3::new( 1.0, 2.0, 3.0 ) );
+    transform.set_axis_angle( Vec3::X, 0.5 );
+    let rotation = transform.rotation;
+
+    transform.look_at( transform.translation, Vec3::Y );
+    assert_eq!( transform.rotation, rotation );
+    transform.look_at( Vec3::new( 1.0, 7.0, 3.0 ), Vec3::Y );
+    assert_eq!( transform.rotation, rotation );
+    transform.look_at( Vec3::new( 1.0, -7.0, 3.0 ), Vec3::Y );
+    assert_eq!( transform.rotation, rotation );
+  }
+
+  #[ test ]
+  fn axis_angle_normalizes_the_axis()
+  {
+    let mut transform = Transform::default();
+    transform.set_axis_angle( Vec3::new( 0.0, 0.0, 2.0 ), FRAC_PI_2 );
+    assert!( close( transform.rotation * Vec3::X, Vec3::Y ) );
+
+    let rotation = transform.rotation;
+    transform.set_axis_angle( Vec3::ZERO, 1.0 );
+    assert_eq!( transform.rotation, rotation );
+  }
+
+  #[ test ]
+  fn euler_angles_round_trip()
+  {
+    let mut transform = Transform::default();
+    transform.set_euler( EulerRot::YXZ, FRAC_PI_2, 0.0, 0.0 );
+    assert!( close( transform.rotation * Vec3::Z, Vec3::X ) );
+
+    transform.set_euler( EulerRot::XYZ, 0.3, -0.2, 0.1 );
+    let ( a, b, c ) = transform.euler( EulerRot::XYZ );
+    assert!( close( Vec3::new( a, b, c ), Vec3::new( 0.3, -0.2, 0.1 ) ) );
+  }
+}
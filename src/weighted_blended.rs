@@ -0,0 +1,220 @@
+//!
+//! Weighted blended order-independent transparency pass, see `oit` for the math.
+//!
+
+use notan::prelude::*;
+use crate::error::Error;
+use crate::lib::{ bind_uniform_blocks, mesh_pipeline, Coverage, Mesh, Occluders, RenderClass, View };
+use crate::screen_quad::ScreenQuad;
+
+const FRAG_ACCUMULATION : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  layout( set = 2, binding = 0 ) uniform MeshMaterial
+  {
+    vec4 tint;
+    float opacity;
+    float premultiplied;
+    float cutoff;
+  };
+
+  float weight( float depth, float alpha )
+  {
+    return alpha * clamp( pow( 1.0 - depth, 3.0 ), 0.01, 1.0 );
+  }
+
+  void main()
+  {
+    vec4 texel = texture( u_texture, v_uv );
+    // The pass works on straight colors, premultiplied texels are divided back by their alpha.
+    texel.rgb /= mix( 1.0, max( texel.a, 0.00001 ), premultiplied );
+    texel *= tint;
+    texel.a *= opacity;
+    if( texel.a < cutoff )
+    {
+      discard;
+    }
+    float w = weight( gl_FragCoord.z, texel.a );
+    color = vec4( texel.rgb * w, w );
+  }
+  "#
+};
+
+const FRAG_REVEALAGE : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_texture;
+  layout( set = 2, binding = 0 ) uniform MeshMaterial
+  {
+    vec4 tint;
+    float opacity;
+    float premultiplied;
+    float cutoff;
+  };
+
+  void main()
+  {
+    float alpha = texture( u_texture, v_uv ).a * tint.a * opacity;
+    if( alpha < cutoff )
+    {
+      discard;
+    }
+    color = vec4( alpha );
+  }
+  "#
+};
+
+const FRAG_COMPOSITE : ShaderSource< '_ > = notan::fragment_shader!
+{
+  r#"
+  #version 450
+  precision mediump float;
+
+  layout( location = 0 ) in vec2 v_uv;
+  layout( location = 0 ) out vec4 color;
+  layout( location = 0 ) uniform sampler2D u_accumulation;
+  layout( location = 1 ) uniform sampler2D u_revealage;
+
+  void main()
+  {
+    vec4 accumulation = texture( u_accumulation, v_uv );
+    float revealage = texture( u_revealage, v_uv ).r;
+    color = vec4( accumulation.rgb / max( accumulation.a, 0.00001 ), 1.0 - revealage );
+  }
+  "#
+};
+
+/// Renders translucent meshes in any order into an accumulation and a revealage target, then
+/// composites the average over the screen.
+///
+/// Notan renders to one target at a time, so accumulation and revealage take a pass each.
+#[ derive( Debug ) ]
+pub struct WeightedBlended
+{
+  accumulation : RenderTexture,
+  revealage : RenderTexture,
+  occluders : Occluders,
+  accumulation_pipeline : Pipeline,
+  revealage_pipeline : Pipeline,
+  composite_pipeline : Pipeline,
+  quad : ScreenQuad,
+}
+
+impl WeightedBlended
+{
+  pub fn new( gfx : &mut Graphics, coverage : Coverage ) -> Result< Self, Error >
+  {
+    let accumulation = Self::target( gfx )?;
+    let revealage = Self::target( gfx )?;
+
+    let depth_test = DepthStencil
+    {
+      write : false,
+      compare : CompareMode::Less,
+    };
+
+    let occluders = Occluders::new( gfx, coverage )?;
+    let accumulation_pipeline = mesh_pipeline( gfx, &FRAG_ACCUMULATION )
+    .with_color_blend( BlendMode::ADD )
+    .with_alpha_blend( BlendMode::ADD )
+    .with_depth_stencil( depth_test )
+    .build().map_err( Error::pipeline )?;
+    let revealage_pipeline = mesh_pipeline( gfx, &FRAG_REVEALAGE )
+    .with_color_blend( BlendMode::new( BlendFactor::Zero, BlendFactor::InverseSourceAlpha ) )
+    .with_alpha_blend( BlendMode::new( BlendFactor::Zero, BlendFactor::InverseSourceAlpha ) )
+    .with_depth_stencil( depth_test )
+    .build().map_err( Error::pipeline )?;
+    bind_uniform_blocks( gfx, &[ &accumulation_pipeline, &revealage_pipeline ] )?;
+
+    let composite_pipeline = ScreenQuad::pipeline( gfx, &FRAG_COMPOSITE )
+    .with_texture_location( 0, "u_accumulation" )
+    .with_texture_location( 1, "u_revealage" )
+    .with_color_blend( BlendMode::NORMAL )
+    .with_alpha_blend( BlendMode::OVER )
+    .build().map_err( Error::pipeline )?;
+
+    let quad = ScreenQuad::new( gfx )?;
+
+    Ok( Self
+    {
+      accumulation,
+      revealage,
+      occluders,
+      accumulation_pipeline,
+      revealage_pipeline,
+      composite_pipeline,
+      quad,
+    })
+  }
+
+  /// Recreate the targets at the size of the window.
+  pub fn resize( &mut self, gfx : &mut Graphics ) -> Result< (), Error >
+  {
+    self.accumulation = Self::target( gfx )?;
+    self.revealage = Self::target( gfx )?;
+    Ok( () )
+  }
+
+  fn target( gfx : &mut Graphics ) -> Result< RenderTexture, Error >
+  {
+    let ( width, height ) = gfx.size();
+    gfx
+    .create_render_texture( width, height )
+    .with_depth()
+    .build().map_err( Error::texture )
+  }
+
+  /// Blend the translucent meshes over the screen, occluded by the opaque and alpha-tested ones.
+  ///
+  /// Expects the camera and the transformations of the meshes to be uploaded.
+  pub fn render( &self, gfx : &mut Graphics, meshes : &[ Mesh ], view : &View< '_ > )
+  {
+    let accumulation = self.target_renderer( gfx, meshes, view, Color::TRANSPARENT, &self.accumulation_pipeline );
+    gfx.render_to( &self.accumulation, &accumulation );
+
+    let revealage = self.target_renderer( gfx, meshes, view, Color::WHITE, &self.revealage_pipeline );
+    gfx.render_to( &self.revealage, &revealage );
+
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( None );
+    renderer.set_pipeline( &self.composite_pipeline );
+    renderer.bind_texture_slot( 0, 0, &self.accumulation );
+    renderer.bind_texture_slot( 1, 1, &self.revealage );
+    self.quad.draw( &mut renderer );
+    renderer.end();
+    gfx.render( &renderer );
+  }
+
+  /// Lay down the depth of the meshes that occlude, then blend the translucent ones without writing depth.
+  fn target_renderer( &self, gfx : &Graphics, meshes : &[ Mesh ], view : &View< '_ >, clear : Color, pipeline : &Pipeline ) -> Renderer
+  {
+    let mut renderer = gfx.create_renderer();
+    renderer.begin( Some( &ClearOptions
+    {
+      color : Some( clear ),
+      depth : Some( 1.0 ),
+      stencil : None,
+    }));
+
+    self.occluders.draw( &mut renderer, meshes, view );
+    renderer.set_pipeline( pipeline );
+    for mesh in meshes.iter().filter( | mesh | mesh.class() == RenderClass::Translucent )
+    {
+      mesh.draw( &mut renderer );
+    }
+
+    renderer.end();
+    renderer
+  }
+}